@@ -5,11 +5,18 @@ struct CustomMaterial {
     time: f32,
     offset: f32,
     selected: f32,
+    glyphs: f32,
+    pulse: f32,
 };
 
 @group(1) @binding(0)
 var<uniform> material: CustomMaterial;
 
+@group(1) @binding(1)
+var symbol_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var symbol_sampler: sampler;
+
 fn Muzzle(uv: vec2<f32>) -> vec4<f32> {
     var u =  vec2<f32>(1.0,.5) - uv;
     let T = floor(material.time * 20.);
@@ -51,12 +58,22 @@ fn fragment(
         a += f;
     }
 
-    a = smoothstep(0.2, 0.4, a) * 0.7;
+    a = smoothstep(0.2, 0.4, a) * (0.7 + material.pulse * 0.3);
+    c *= 1.0 + material.pulse * 0.5;
 
     // add a white border to the selected one
     var sel = material.selected * cubicPulse(0.3, 0.1, a);
     a += sel;
     c += vec3(sel);
 
+    // symbols scrolling along the beam, one every three beam widths
+    // the beam is 25 times longer than it is wide
+    let cell = uv.x * 25.0 / 3.0 - material.time * 0.5;
+    let glyph_uv = vec2(fract(cell) * 3.0, uv.y);
+    let in_cell = step(glyph_uv.x, 1.0);
+    let s = textureSample(symbol_texture, symbol_sampler, min(glyph_uv, vec2(1.0))).a * in_cell * material.glyphs;
+    c = mix(c, vec3(1.0), s * 0.8);
+    a = max(a, s);
+
     return vec4(c, a);
 }
@@ -4,6 +4,13 @@ struct CustomMaterial {
     color: vec4<f32>,
     time: f32,
     damaged: f32,
+    pulse: f32,
+    dissolve: f32,
+    progress: f32,
+    beams: vec4<f32>,
+    red: vec4<f32>,
+    green: vec4<f32>,
+    blue: vec4<f32>,
 };
 
 @group(1) @binding(0)
@@ -14,12 +21,29 @@ var symbol_texture: texture_2d<f32>;
 @group(1) @binding(2)
 var symbol_sampler: sampler;
 
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// a dot in a corner for a beam the enemy needs, filled in while it's hitting
+fn beam_pip(c: vec4<f32>, uv: vec2<f32>, at: vec2<f32>, state: f32, color: vec4<f32>) -> vec4<f32> {
+    let d = distance(uv, at);
+    if (state <= 0.0 || d > 0.09) {
+        return c;
+    }
+    if (state < 1.0 && d < 0.06) {
+        return vec4(color.rgb * 0.2, 1.0);
+    }
+    return vec4(color.rgb, 1.0);
+}
+
 @fragment
 fn fragment(
     @builtin(position) position: vec4<f32>,
     #import bevy_sprite::mesh2d_vertex_output
 ) -> @location(0) vec4<f32> {
     var c = material.color;
+    c = vec4(c.rgb * (1.0 + material.pulse * 0.3), c.a);
     c.a -= material.damaged * (sin(material.time * 6.0) * 0.2 + 0.2);
 
     let s = textureSample(symbol_texture, symbol_sampler, uv).a;
@@ -27,5 +51,33 @@ fn fragment(
 
     c = mix(c, sc, s);
 
+    // kill progress fills a ring clockwise from the top
+    let centre = uv - vec2<f32>(0.5, 0.5);
+    let r = length(centre);
+    if (material.progress > 0.0 && r > 0.38 && r < 0.48) {
+        let t = fract(atan2(centre.x, -centre.y) / 6.2831853 + 1.0);
+        if (t < material.progress) {
+            c = vec4(1.0, 1.0, 1.0, 1.0);
+        } else {
+            c = vec4(c.rgb * 0.4, c.a);
+        }
+    }
+
+    c = beam_pip(c, uv, vec2<f32>(0.1, 0.1), material.beams.x, material.red);
+    c = beam_pip(c, uv, vec2<f32>(0.9, 0.1), material.beams.y, material.green);
+    c = beam_pip(c, uv, vec2<f32>(0.9, 0.9), material.beams.z, material.blue);
+
+    // break into blocks that disappear one by one
+    if (material.dissolve > 0.0) {
+        let n = hash(floor(uv * 6.0));
+        if (n < material.dissolve) {
+            discard;
+        }
+        // the blocks about to go glow
+        if (n < material.dissolve + 0.2) {
+            c = vec4(mix(c.rgb, vec3(1.0), 0.8), c.a);
+        }
+    }
+
     return c;
 }
@@ -0,0 +1,223 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    #[default]
+    Loop,
+    /// plays once and then goes to `next`, or stays on the last frame
+    Once,
+    /// back and forth
+    PingPong,
+}
+
+fn default_duration() -> f32 {
+    0.1
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Clip {
+    /// name in `SpriteSheets`
+    pub sheet: String,
+    /// first and last atlas index, both included
+    pub frames: (usize, usize),
+    /// seconds each frame is shown, unless `durations` says otherwise
+    #[serde(default = "default_duration")]
+    pub duration: f32,
+    /// per frame durations, frames past the end use `duration`
+    #[serde(default)]
+    pub durations: Vec<f32>,
+    #[serde(default)]
+    pub mode: LoopMode,
+    /// clip to go to when a `Once` clip is over
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+impl Clip {
+    fn len(&self) -> usize {
+        self.frames.1.saturating_sub(self.frames.0) + 1
+    }
+
+    fn frame_duration(&self, frame: usize) -> f32 {
+        self.durations.get(frame).copied().unwrap_or(self.duration)
+    }
+}
+
+/// the clips a sprite can play, loaded from a `.anim` file written in ron
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "b5a1c7e2-3f4d-4a8b-9c6e-2d7f1e0a3b4c"]
+pub struct AnimationSet {
+    pub clips: HashMap<String, Clip>,
+}
+
+#[derive(Default)]
+pub struct AnimationSetLoader;
+
+impl AssetLoader for AnimationSetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let set: AnimationSet = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(set));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim"]
+    }
+}
+
+#[derive(AssetCollection)]
+pub struct AnimationAssets {
+    #[asset(path = "sprites/player.anim")]
+    pub player: Handle<AnimationSet>,
+    #[asset(path = "sprites/enemy.anim")]
+    pub enemy: Handle<AnimationSet>,
+}
+
+/// the texture atlases clips can use, by name
+pub struct SpriteSheets(HashMap<&'static str, Handle<TextureAtlas>>);
+
+impl FromWorld for SpriteSheets {
+    fn from_world(world: &mut World) -> Self {
+        let player = world.resource::<PlayerAssets>();
+        let mut sheets = HashMap::default();
+        sheets.insert("player", player.player.clone());
+        sheets.insert("player-neutral", player.player_neutral.clone());
+        sheets.insert("player-sad", player.player_sad.clone());
+        sheets.insert("enemy", world.resource::<EnemyAssets>().enemy.clone());
+        Self(sheets)
+    }
+}
+
+/// plays clips from an `AnimationSet` on a sprite
+#[derive(Component)]
+pub struct Animator {
+    set: Handle<AnimationSet>,
+    clip: String,
+    frame: usize,
+    elapsed: f32,
+    /// for ping pong clips
+    backwards: bool,
+    /// a `Once` clip that reached its end
+    finished: bool,
+    /// a `Once` clip is playing, and shouldn't be interrupted by the usual state changes
+    pub busy: bool,
+    /// stays on the first frame
+    pub paused: bool,
+}
+
+impl Animator {
+    pub fn new(set: Handle<AnimationSet>, clip: &str) -> Self {
+        Self {
+            set,
+            clip: clip.to_string(),
+            frame: 0,
+            elapsed: 0.0,
+            backwards: false,
+            finished: false,
+            busy: false,
+            paused: false,
+        }
+    }
+
+    /// switches to a clip, unless it's already playing
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            self.clip = clip.to_string();
+            self.frame = 0;
+            self.elapsed = 0.0;
+            self.backwards = false;
+            self.finished = false;
+        }
+    }
+}
+
+pub fn animate_sprites(
+    time: Res<Time>,
+    sets: Res<Assets<AnimationSet>>,
+    sheets: Res<SpriteSheets>,
+    mut query: Query<(
+        &mut Animator,
+        &mut TextureAtlasSprite,
+        &mut Handle<TextureAtlas>,
+    )>,
+) {
+    for (mut animator, mut sprite, mut atlas) in &mut query {
+        let set = match sets.get(&animator.set) {
+            Some(s) => s,
+            None => continue,
+        };
+        let mut clip = match set.clips.get(&animator.clip) {
+            Some(c) => c,
+            None => continue,
+        };
+
+        if animator.paused {
+            animator.frame = 0;
+            animator.elapsed = 0.0;
+        } else if !animator.finished {
+            animator.elapsed += time.delta_seconds();
+            loop {
+                let duration = clip.frame_duration(animator.frame);
+                if duration <= 0.0 || animator.elapsed < duration {
+                    break;
+                }
+                animator.elapsed -= duration;
+
+                let len = clip.len();
+                match clip.mode {
+                    LoopMode::Loop => animator.frame = (animator.frame + 1) % len,
+                    LoopMode::PingPong if len > 1 => {
+                        if animator.frame == len - 1 {
+                            animator.backwards = true;
+                        } else if animator.frame == 0 {
+                            animator.backwards = false;
+                        }
+                        if animator.backwards {
+                            animator.frame -= 1;
+                        } else {
+                            animator.frame += 1;
+                        }
+                    }
+                    LoopMode::PingPong => {}
+                    LoopMode::Once if animator.frame + 1 < len => animator.frame += 1,
+                    LoopMode::Once => match clip.next.as_ref().and_then(|n| set.clips.get(n)) {
+                        Some(next) => {
+                            let name = clip.next.clone().unwrap();
+                            animator.play(&name);
+                            clip = next;
+                        }
+                        None => {
+                            animator.finished = true;
+                            break;
+                        }
+                    },
+                }
+            }
+        }
+
+        animator.busy = clip.mode == LoopMode::Once && !animator.finished;
+        // the clip might have changed, or the file might have been edited
+        animator.frame = animator.frame.min(clip.len() - 1);
+        let index = clip.frames.0 + animator.frame;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+        if let Some(sheet) = sheets.0.get(clip.sheet.as_str()) {
+            if *atlas != *sheet {
+                *atlas = sheet.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::*;
+use bevy::render::camera::ScalingMode;
+
+/// width of the playable field, in world units
+pub const ARENA_WIDTH: f32 = 1280.0;
+/// height of the playable field, in world units
+pub const ARENA_HEIGHT: f32 = 720.0;
+
+pub const PIVOT_X: f32 = -500.0;
+pub const LANES: [f32; 3] = [120.0, 0.0, -120.0];
+
+pub const PLAYER_MIN: Vec2 = Vec2::new(-600.0, -120.0);
+pub const PLAYER_MAX: Vec2 = Vec2::new(-525.0, 140.0);
+
+/// x coordinate where enemies appear, just past the right edge of the arena
+pub const SPAWN_X: f32 = ARENA_WIDTH / 2.0 + 50.0;
+
+const LETTERBOX_SIZE: f32 = 10000.0;
+
+pub fn spawn_camera(commands: &mut Commands) {
+    let mut camera = Camera2dBundle::default();
+    // always show the whole arena, the extra space is covered by the letterbox
+    camera.projection.scaling_mode = ScalingMode::Auto {
+        min_width: ARENA_WIDTH,
+        min_height: ARENA_HEIGHT,
+    };
+
+    commands.spawn_bundle(camera).insert(MainCamera);
+}
+
+/// covers everything outside of the arena with black bars
+pub fn spawn_letterbox(commands: &mut Commands) {
+    let offset_x = (ARENA_WIDTH + LETTERBOX_SIZE) / 2.0;
+    let offset_y = (ARENA_HEIGHT + LETTERBOX_SIZE) / 2.0;
+    for pos in [
+        Vec2::new(-offset_x, 0.0),
+        Vec2::new(offset_x, 0.0),
+        Vec2::new(0.0, -offset_y),
+        Vec2::new(0.0, offset_y),
+    ] {
+        commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::BLACK,
+                custom_size: Some(Vec2::splat(LETTERBOX_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(pos.extend(100.0)),
+            ..default()
+        });
+    }
+}
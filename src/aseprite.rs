@@ -0,0 +1,559 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::BoxedFuture,
+};
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+const CHUNK_SLICE: u16 = 0x2022;
+
+/// a sprite loaded straight from an `.aseprite` file
+///
+/// the frames are laid out in a single row. labeled sub-assets:
+/// - `atlas`: the `TextureAtlas` of all the frames
+/// - `animations`: an `AnimationSet` with a clip for each tag
+/// - `frame<n>`: each frame on its own
+/// - `slice-<name>`: each slice, cut out of the frame of its first key
+#[derive(TypeUuid)]
+#[uuid = "4e7d2a9c-1b3f-4c6d-8e0a-5f2b7c9d1e3a"]
+pub struct Aseprite {
+    pub atlas: Handle<TextureAtlas>,
+    pub frame_size: Vec2,
+    pub frames: usize,
+}
+
+#[derive(Default)]
+pub struct AsepriteLoader;
+
+impl AssetLoader for AsepriteLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let file = AsepriteFile::parse(bytes)?;
+            let (w, h) = (file.width, file.height);
+            let frames = file
+                .frames
+                .iter()
+                .map(|f| file.render(f))
+                .collect::<Vec<_>>();
+
+            // all the frames in a row
+            let mut strip = vec![0; w * h * 4 * frames.len()];
+            for (i, frame) in frames.iter().enumerate() {
+                for y in 0..h {
+                    let from = y * w * 4;
+                    let to = (y * w * frames.len() + i * w) * 4;
+                    strip[to..to + w * 4].copy_from_slice(&frame[from..from + w * 4]);
+                }
+            }
+            let image = load_context.set_labeled_asset(
+                "image",
+                LoadedAsset::new(rgba_image(w * frames.len(), h, strip)),
+            );
+            let frame_size = Vec2::new(w as f32, h as f32);
+            let atlas = load_context.set_labeled_asset(
+                "atlas",
+                LoadedAsset::new(TextureAtlas::from_grid(image, frame_size, frames.len(), 1)),
+            );
+
+            for (i, frame) in frames.iter().enumerate() {
+                load_context.set_labeled_asset(
+                    &format!("frame{i}"),
+                    LoadedAsset::new(rgba_image(w, h, frame.clone())),
+                );
+            }
+
+            for slice in &file.slices {
+                let frame = match frames.get(slice.frame) {
+                    Some(f) => f,
+                    None => continue,
+                };
+                let (x0, y0) = (slice.x.max(0) as usize, slice.y.max(0) as usize);
+                let sw = slice.width.min(w.saturating_sub(x0));
+                let sh = slice.height.min(h.saturating_sub(y0));
+                let mut data = vec![0; sw * sh * 4];
+                for y in 0..sh {
+                    let from = ((y0 + y) * w + x0) * 4;
+                    data[y * sw * 4..(y + 1) * sw * 4].copy_from_slice(&frame[from..from + sw * 4]);
+                }
+                load_context.set_labeled_asset(
+                    &format!("slice-{}", slice.name),
+                    LoadedAsset::new(rgba_image(sw, sh, data)),
+                );
+            }
+
+            // clips play on the sheet named after the file, like "player"
+            let sheet = load_context
+                .path()
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let clips = file
+                .tags
+                .iter()
+                .map(|tag| {
+                    let durations = (tag.from..=tag.to)
+                        .filter_map(|i| file.frames.get(i))
+                        .map(|f| f.duration)
+                        .collect::<Vec<_>>();
+                    let clip = Clip {
+                        sheet: sheet.clone(),
+                        frames: (tag.from, tag.to),
+                        duration: durations.first().copied().unwrap_or(0.1),
+                        durations,
+                        // reverse tags play forwards, there's no reverse mode
+                        mode: match (tag.repeat, tag.direction) {
+                            (1, _) => LoopMode::Once,
+                            (_, 2 | 3) => LoopMode::PingPong,
+                            _ => LoopMode::Loop,
+                        },
+                        next: None,
+                    };
+                    (tag.name.clone(), clip)
+                })
+                .collect();
+            load_context.set_labeled_asset("animations", LoadedAsset::new(AnimationSet { clips }));
+
+            load_context.set_default_asset(LoadedAsset::new(Aseprite {
+                atlas,
+                frame_size,
+                frames: frames.len(),
+            }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite", "ase"]
+    }
+}
+
+fn rgba_image(width: usize, height: usize, data: Vec<u8>) -> Image {
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// reads the little endian values the format is made of
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| Error::msg("aseprite file ends too early"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, Error> {
+        Ok(i16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        self.pos = self.bytes.len();
+        rest
+    }
+}
+
+struct Layer {
+    visible: bool,
+    /// groups and reference layers don't draw anything
+    drawn: bool,
+    opacity: u8,
+}
+
+enum CelPixels {
+    Image {
+        width: usize,
+        height: usize,
+        /// in the file's colour depth
+        data: Vec<u8>,
+    },
+    /// the same as the cel on this layer in another frame
+    Linked(usize),
+}
+
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    pixels: CelPixels,
+}
+
+struct Frame {
+    /// in seconds
+    duration: f32,
+    cels: Vec<Cel>,
+}
+
+struct Tag {
+    name: String,
+    from: usize,
+    to: usize,
+    /// 0 forward, 1 reverse, 2 ping pong, 3 ping pong reverse
+    direction: u8,
+    /// 0 for forever
+    repeat: u16,
+}
+
+struct Slice {
+    name: String,
+    frame: usize,
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+}
+
+struct AsepriteFile {
+    width: usize,
+    height: usize,
+    /// bits per pixel: 32 rgba, 16 grayscale or 8 indexed
+    depth: u16,
+    transparent_index: u8,
+    palette: Vec<[u8; 4]>,
+    layers: Vec<Layer>,
+    frames: Vec<Frame>,
+    tags: Vec<Tag>,
+    slices: Vec<Slice>,
+}
+
+impl AsepriteFile {
+    /// see https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+    fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut r = Reader::new(bytes);
+        r.skip(4)?;
+        if r.u16()? != HEADER_MAGIC {
+            return Err(Error::msg("not an aseprite file"));
+        }
+        let frame_count = r.u16()? as usize;
+        let width = r.u16()? as usize;
+        let height = r.u16()? as usize;
+        let depth = r.u16()?;
+        if ![32, 16, 8].contains(&depth) {
+            return Err(Error::msg(format!("unknown colour depth {depth}")));
+        }
+        r.skip(14)?;
+        let transparent_index = r.u8()?;
+        r.skip(128 - r.pos)?;
+
+        let mut file = Self {
+            width,
+            height,
+            depth,
+            transparent_index,
+            palette: vec![],
+            layers: vec![],
+            frames: vec![],
+            tags: vec![],
+            slices: vec![],
+        };
+
+        for _ in 0..frame_count {
+            let start = r.pos;
+            let size = r.u32()? as usize;
+            if r.u16()? != FRAME_MAGIC {
+                return Err(Error::msg("broken frame header"));
+            }
+            let old_chunks = r.u16()? as usize;
+            let duration = r.u16()? as f32 / 1000.0;
+            r.skip(2)?;
+            let chunks = match r.u32()? as usize {
+                0 => old_chunks,
+                n => n,
+            };
+
+            let mut frame = Frame {
+                duration,
+                cels: vec![],
+            };
+            for _ in 0..chunks {
+                let chunk_size = r.u32()? as usize;
+                let kind = r.u16()?;
+                let mut c = Reader::new(r.bytes(chunk_size.saturating_sub(6))?);
+                match kind {
+                    CHUNK_LAYER => file.layers.push(parse_layer(&mut c)?),
+                    CHUNK_CEL => frame.cels.extend(parse_cel(&mut c)?),
+                    CHUNK_TAGS => file.tags.extend(parse_tags(&mut c)?),
+                    CHUNK_SLICE => file.slices.extend(parse_slice(&mut c)?),
+                    CHUNK_PALETTE => parse_palette(&mut c, &mut file.palette)?,
+                    // only used when there's no new palette
+                    CHUNK_OLD_PALETTE if file.palette.is_empty() => {
+                        parse_old_palette(&mut c, &mut file.palette)?
+                    }
+                    _ => {}
+                }
+            }
+            file.frames.push(frame);
+            r.pos = start + size;
+        }
+
+        Ok(file)
+    }
+
+    /// flattens the visible layers of a frame into rgba
+    fn render(&self, frame: &Frame) -> Vec<u8> {
+        let mut out = vec![0; self.width * self.height * 4];
+        let mut cels = frame.cels.iter().collect::<Vec<_>>();
+        cels.sort_by_key(|c| c.layer);
+
+        for cel in cels {
+            let layer = match self.layers.get(cel.layer) {
+                Some(l) if l.visible && l.drawn => l,
+                _ => continue,
+            };
+            let (width, height, data) = match self.cel_pixels(cel) {
+                Some(p) => p,
+                None => continue,
+            };
+            let opacity = cel.opacity as f32 / 255.0 * layer.opacity as f32 / 255.0;
+
+            for y in 0..height {
+                for x in 0..width {
+                    let (ox, oy) = (cel.x + x as i32, cel.y + y as i32);
+                    if ox < 0 || oy < 0 || ox as usize >= self.width || oy as usize >= self.height {
+                        continue;
+                    }
+                    let src = self.pixel(data, y * width + x);
+                    let i = (oy as usize * self.width + ox as usize) * 4;
+                    blend(&mut out[i..i + 4], src, opacity);
+                }
+            }
+        }
+        out
+    }
+
+    fn cel_pixels<'a>(&'a self, cel: &'a Cel) -> Option<(usize, usize, &'a [u8])> {
+        match &cel.pixels {
+            CelPixels::Image {
+                width,
+                height,
+                data,
+            } => Some((*width, *height, data)),
+            CelPixels::Linked(frame) => {
+                let linked = self.frames.get(*frame)?.cels.iter().find(|c| {
+                    c.layer == cel.layer && matches!(c.pixels, CelPixels::Image { .. })
+                })?;
+                self.cel_pixels(linked)
+            }
+        }
+    }
+
+    fn pixel(&self, data: &[u8], index: usize) -> [u8; 4] {
+        match self.depth {
+            32 => data
+                .get(index * 4..index * 4 + 4)
+                .and_then(|p| p.try_into().ok())
+                .unwrap_or_default(),
+            16 => match data.get(index * 2..index * 2 + 2) {
+                Some(&[v, a]) => [v, v, v, a],
+                _ => [0; 4],
+            },
+            _ => match data.get(index) {
+                Some(i) if *i != self.transparent_index => {
+                    self.palette.get(*i as usize).copied().unwrap_or_default()
+                }
+                _ => [0; 4],
+            },
+        }
+    }
+}
+
+/// draws `src` over `dst`, both not premultiplied
+fn blend(dst: &mut [u8], src: [u8; 4], opacity: f32) {
+    let sa = src[3] as f32 / 255.0 * opacity;
+    if sa <= 0.0 {
+        return;
+    }
+    let da = dst[3] as f32 / 255.0;
+    let a = sa + da * (1.0 - sa);
+    for c in 0..3 {
+        let v = (src[c] as f32 * sa + dst[c] as f32 * da * (1.0 - sa)) / a;
+        dst[c] = v.round() as u8;
+    }
+    dst[3] = (a * 255.0).round() as u8;
+}
+
+fn parse_layer(c: &mut Reader) -> Result<Layer, Error> {
+    let flags = c.u16()?;
+    let kind = c.u16()?;
+    c.skip(8)?;
+    let opacity = c.u8()?;
+    Ok(Layer {
+        visible: flags & 1 != 0,
+        // 1 is a group, 64 a reference layer
+        drawn: kind != 1 && flags & 64 == 0,
+        opacity,
+    })
+}
+
+fn parse_cel(c: &mut Reader) -> Result<Option<Cel>, Error> {
+    let layer = c.u16()? as usize;
+    let x = c.i16()? as i32;
+    let y = c.i16()? as i32;
+    let opacity = c.u8()?;
+    let kind = c.u16()?;
+    c.skip(7)?;
+
+    let pixels = match kind {
+        0 | 2 => {
+            let width = c.u16()? as usize;
+            let height = c.u16()? as usize;
+            let data = if kind == 0 {
+                c.rest().to_vec()
+            } else {
+                miniz_oxide::inflate::decompress_to_vec_zlib(c.rest())
+                    .map_err(|e| Error::msg(format!("broken cel: {e:?}")))?
+            };
+            CelPixels::Image {
+                width,
+                height,
+                data,
+            }
+        }
+        1 => CelPixels::Linked(c.u16()? as usize),
+        // tilemaps aren't supported
+        _ => return Ok(None),
+    };
+    Ok(Some(Cel {
+        layer,
+        x,
+        y,
+        opacity,
+        pixels,
+    }))
+}
+
+fn parse_tags(c: &mut Reader) -> Result<Vec<Tag>, Error> {
+    let count = c.u16()?;
+    c.skip(8)?;
+    let mut tags = vec![];
+    for _ in 0..count {
+        let from = c.u16()? as usize;
+        let to = c.u16()? as usize;
+        let direction = c.u8()?;
+        let repeat = c.u16()?;
+        c.skip(10)?;
+        tags.push(Tag {
+            name: c.string()?,
+            from,
+            to,
+            direction,
+            repeat,
+        });
+    }
+    Ok(tags)
+}
+
+fn parse_slice(c: &mut Reader) -> Result<Option<Slice>, Error> {
+    let keys = c.u32()?;
+    c.skip(8)?;
+    let name = c.string()?;
+    if keys == 0 {
+        return Ok(None);
+    }
+    // only the first key, slices that move around aren't supported
+    Ok(Some(Slice {
+        name,
+        frame: c.u32()? as usize,
+        x: c.i32()?,
+        y: c.i32()?,
+        width: c.u32()? as usize,
+        height: c.u32()? as usize,
+    }))
+}
+
+fn parse_palette(c: &mut Reader, palette: &mut Vec<[u8; 4]>) -> Result<(), Error> {
+    let size = c.u32()? as usize;
+    let first = c.u32()? as usize;
+    let last = c.u32()? as usize;
+    c.skip(8)?;
+    palette.resize(size.max(palette.len()), [0; 4]);
+    for i in first..=last {
+        let flags = c.u16()?;
+        let colour = [c.u8()?, c.u8()?, c.u8()?, c.u8()?];
+        if flags & 1 != 0 {
+            c.string()?;
+        }
+        if let Some(entry) = palette.get_mut(i) {
+            *entry = colour;
+        }
+    }
+    Ok(())
+}
+
+fn parse_old_palette(c: &mut Reader, palette: &mut Vec<[u8; 4]>) -> Result<(), Error> {
+    let packets = c.u16()?;
+    let mut index = 0;
+    for _ in 0..packets {
+        index += c.u8()? as usize;
+        let count = match c.u8()? {
+            0 => 256,
+            n => n as usize,
+        };
+        for _ in 0..count {
+            let colour = [c.u8()?, c.u8()?, c.u8()?, 255];
+            if palette.len() <= index {
+                palette.resize(index + 1, [0; 4]);
+            }
+            palette[index] = colour;
+            index += 1;
+        }
+    }
+    Ok(())
+}
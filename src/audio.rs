@@ -1,52 +1,235 @@
 use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    audio::AudioSink,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
 
-pub fn play_audio_when_enemy_dies(
+/// notes are placed on eighth notes
+const NOTES_PER_BEAT: f64 = 2.0;
+
+/// a kill sound waiting for its slot on the beat grid
+pub struct ScheduledNote {
+    at: f64,
+    note: usize,
+    position: Vec2,
+}
+
+#[derive(Default, Deref, DerefMut)]
+pub struct ScheduledNotes(Vec<ScheduledNote>);
+
+pub fn schedule_notes_when_enemy_dies(
+    mut enemy_death: EventReader<EnemyDead>,
     a: Res<AudioAssets>,
-    enemy_death: EventReader<EnemyDead>,
-    audio: Res<Audio>,
+    melodies: Res<Assets<Melodies>>,
+    clock: Res<MusicClock>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+    mut scheduled: ResMut<ScheduledNotes>,
     mut idx: Local<usize>,
 ) {
-    let melody = [
-        &a.enemy_killed_sound_c,
-        &a.enemy_killed_sound_g,
-        &a.enemy_killed_sound_e,
-        &a.enemy_killed_sound_d,
-        &a.enemy_killed_sound_c,
-        &a.enemy_killed_sound_e,
-        &a.enemy_killed_sound_d,
-        &a.enemy_killed_sound_f,
-        &a.enemy_killed_sound_c,
-        &a.enemy_killed_sound_e,
-        &a.enemy_killed_sound_d,
-        &a.enemy_killed_sound_g,
-        &a.enemy_killed_sound_c,
-        &a.enemy_killed_sound_f,
-        &a.enemy_killed_sound_e,
-        &a.enemy_killed_sound_d,
-    ];
-
-    if enemy_death.is_empty() {
-        return;
-    }
-    enemy_death.clear();
-
-    let music = melody[*idx % melody.len()].clone();
-    audio.play(music);
-    *idx += 1;
+    let melody = match melodies.get(&a.melodies) {
+        Some(m) => m.for_wave(current_wave(time.seconds_since_startup() - game_start.0)),
+        None => return,
+    };
+
+    let at = clock.next_subdivision(time.seconds_since_startup(), NOTES_PER_BEAT);
+    for dead in enemy_death.iter() {
+        let root = melody[*idx % melody.len()];
+        *idx += 1;
+
+        // mixed colours play a chord made of their primaries
+        for beam in dead.colour.made_by() {
+            scheduled.push(ScheduledNote {
+                at,
+                note: root + beam.degree(),
+                position: dead.position,
+            });
+        }
+    }
+}
+
+pub fn play_scheduled_notes(
+    audio: Res<Audio<Synth>>,
+    mut synths: ResMut<Assets<Synth>>,
+    time: Res<Time>,
+    mut scheduled: ResMut<ScheduledNotes>,
+    camera: Query<&Transform, With<MainCamera>>,
+) {
+    let listener = camera.single().translation.xy();
+    let now = time.seconds_since_startup();
+    scheduled.retain(|n| {
+        if n.at > now {
+            return true;
+        }
+
+        let synth = Synth::new(Patch::Chime {
+            frequency: scale_frequency(n.note),
+        });
+        audio.play(synths.add(synth.at(n.position, listener)));
+        false
+    });
+}
+
+/// notes still waiting when the run ends would otherwise play in the next one
+pub fn clear_scheduled_notes(mut scheduled: ResMut<ScheduledNotes>) {
+    scheduled.clear();
+}
+
+pub fn play_hit_sound(
+    mut hits: EventReader<PlayerHit>,
+    audio: Res<Audio<Synth>>,
+    mut synths: ResMut<Assets<Synth>>,
+    camera: Query<&Transform, With<MainCamera>>,
+) {
+    let listener = camera.single().translation.xy();
+    for hit in hits.iter() {
+        let synth = Synth::new(Patch::Buzz).at(hit.position, listener);
+        audio.play(synths.add(synth));
+    }
+}
+
+/// the continuous sound of the selected beam
+#[derive(Default)]
+pub struct BeamHum {
+    frequency: Param,
+    volume: Param,
+    sink: Option<Handle<AudioSink>>,
+}
+
+pub fn start_beam_hum(
+    mut hum: ResMut<BeamHum>,
+    audio: Res<Audio<Synth>>,
+    mut synths: ResMut<Assets<Synth>>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    hum.frequency = Param::new(220.0);
+    hum.volume = Param::new(0.0);
+    let sink = audio.play(synths.add(Synth::new(Patch::Hum {
+        frequency: hum.frequency.clone(),
+        volume: hum.volume.clone(),
+    })));
+    hum.sink = Some(sinks.get_handle(sink));
+}
+
+pub fn stop_beam_hum(mut hum: ResMut<BeamHum>, sinks: Res<Assets<AudioSink>>) {
+    if let Some(sink) = hum.sink.take().and_then(|s| sinks.get(&s)) {
+        sink.stop();
+    }
+}
+
+pub fn update_beam_hum(
+    hum: Res<BeamHum>,
+    beams: Query<(&Transform, &BeamColor)>,
+    players: Query<(&Player, &ClosestBeam)>,
+    mouse: Res<MousePos>,
+    mut last_mouse: Local<Vec2>,
+    time: Res<Time>,
+    net: Res<NetSession>,
+) {
+    // the hum follows the local player's beam, the one the mouse moves
+    let local = net.local_player();
+    let color = match players.iter().find(|(p, _)| p.index == local) {
+        Some((_, color)) => color.0,
+        None => return,
+    };
+    for (trans, beam) in &beams {
+        if *beam != color {
+            continue;
+        }
+
+        // pointing down is an octave below pointing straight, pointing up an octave above
+        let (_, _, angle) = trans.rotation.to_euler(EulerRot::XYZ);
+        let octaves = angle / std::f32::consts::FRAC_PI_2;
+        hum.frequency.set(220.0 * 2f32.powf(octaves));
+    }
+
+    // louder while the beam is moving
+    let speed = mouse.pos.distance(*last_mouse) / time.delta_seconds().max(0.001);
+    hum.volume.set(0.3 + (speed / 1000.0).min(0.7));
+    *last_mouse = mouse.pos;
+}
+
+impl BeamColor {
+    /// how many scale steps above the melody note this colour sounds
+    fn degree(&self) -> usize {
+        match self {
+            BeamColor::Red => 0,
+            BeamColor::Green => 2,
+            BeamColor::Blue => 4,
+        }
+    }
+}
+
+/// kill melodies, one per wave, loaded from a `.melody` file
+///
+/// each non-empty line that doesn't start with `#` is a melody,
+/// made of the note names `c d e f g a b` separated by spaces
+#[derive(TypeUuid)]
+#[uuid = "8c1d3f7a-2b4e-4d6a-9f1c-5e7a3b2d4c6e"]
+pub struct Melodies(Vec<Vec<usize>>);
+
+impl Melodies {
+    fn parse(s: &str) -> Result<Self, bevy::asset::Error> {
+        let melodies = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                l.split_whitespace()
+                    .map(|note| match note {
+                        "c" => Ok(0),
+                        "d" => Ok(1),
+                        "e" => Ok(2),
+                        "f" => Ok(3),
+                        "g" => Ok(4),
+                        "a" => Ok(5),
+                        "b" => Ok(6),
+                        _ => Err(bevy::asset::Error::msg(format!("unknown note `{note}`"))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if melodies.is_empty() {
+            return Err(bevy::asset::Error::msg("no melodies in file"));
+        }
+        Ok(Self(melodies))
+    }
+
+    pub fn for_wave(&self, wave: usize) -> &[usize] {
+        &self.0[(wave - 1).min(self.0.len() - 1)]
+    }
+}
+
+#[derive(Default)]
+pub struct MelodiesLoader;
+
+impl AssetLoader for MelodiesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let melodies = Melodies::parse(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(melodies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["melody"]
+    }
 }
 
 #[derive(AssetCollection)]
 pub struct AudioAssets {
     #[asset(path = "sounds/music.ogg")]
     pub music: Handle<AudioSource>,
-    #[asset(path = "sounds/enemy-c.ogg")]
-    enemy_killed_sound_c: Handle<AudioSource>,
-    #[asset(path = "sounds/enemy-d.ogg")]
-    enemy_killed_sound_d: Handle<AudioSource>,
-    #[asset(path = "sounds/enemy-e.ogg")]
-    enemy_killed_sound_e: Handle<AudioSource>,
-    #[asset(path = "sounds/enemy-f.ogg")]
-    enemy_killed_sound_f: Handle<AudioSource>,
-    #[asset(path = "sounds/enemy-g.ogg")]
-    enemy_killed_sound_g: Handle<AudioSource>,
+    #[asset(path = "sounds/music.beats")]
+    pub music_beats: Handle<BeatMap>,
+    #[asset(path = "melodies/waves.melody")]
+    melodies: Handle<Melodies>,
 }
@@ -5,36 +5,55 @@ use bevy::{
     sprite::Material2d,
 };
 
+/// the beam a player controls
+#[derive(Component)]
 pub struct ClosestBeam(pub BeamColor);
 pub fn update_closest_beam(
-    player: Query<&Transform, (With<Player>, Without<BeamColor>)>,
+    mut players: Query<(&Transform, &Player, &mut ClosestBeam), Without<BeamColor>>,
     beams: Query<(&Pivot, &BeamColor)>,
-    mut color: ResMut<ClosestBeam>,
+    modifiers: Res<RunModifiers>,
 ) {
-    let pos = player.single().translation.xy();
-    let mut beams = beams.iter().collect::<Vec<_>>();
-    beams.sort_unstable_by(|a, b| {
-        b.0 .0
-            .distance(pos)
-            .partial_cmp(&a.0 .0.distance(pos))
-            .unwrap()
-    });
-    color.0 = *beams.pop().unwrap().1;
+    let mut players = players.iter_mut().collect::<Vec<_>>();
+    players.sort_unstable_by_key(|(_, player, _)| player.index);
+
+    // the first player gets first pick, so two players never share a beam
+    let mut taken = vec![];
+    for (trans, _, mut color) in players {
+        let pos = trans.translation.xy();
+        let mut beams = beams
+            .iter()
+            .filter(|(_, beam)| modifiers.locked_beam != Some(**beam) && !taken.contains(*beam))
+            .collect::<Vec<_>>();
+        beams.sort_unstable_by(|a, b| {
+            b.0 .0
+                .distance(pos)
+                .partial_cmp(&a.0 .0.distance(pos))
+                .unwrap()
+        });
+        if let Some((_, beam)) = beams.pop() {
+            if color.0 != *beam {
+                color.0 = *beam;
+            }
+            taken.push(*beam);
+        }
+    }
 }
 
 pub fn move_light_beam(
     mut query: Query<(&mut Transform, &Pivot, &BeamColor)>,
-    color: Res<ClosestBeam>,
-    mouse: Res<MousePos>,
+    players: Query<(&ClosestBeam, &Aim)>,
+    modifiers: Res<RunModifiers>,
 ) {
     for (mut trans, pivot, beam) in &mut query {
-        if *beam == color.0 {
-            //
-            let diff =
-                Vec2::new((mouse.pos.x - pivot.0.x).abs(), mouse.pos.y - pivot.0.y).normalize();
+        for (color, aim) in &players {
+            if *beam != color.0 {
+                continue;
+            }
+
+            let diff = Vec2::new((aim.0.x - pivot.0.x).abs(), aim.0.y - pivot.0.y).normalize();
             let angle = diff.angle_between(Vec2::X);
 
-            trans.translation = (pivot.0 + diff * BEAM_LENGTH / 2.0).extend(0.0);
+            trans.translation = (pivot.0 + diff * modifiers.scaled_beam_length() / 2.0).extend(0.0);
             trans.rotation = Quat::from_rotation_z(-angle);
         }
     }
@@ -57,19 +76,49 @@ pub struct BeamMaterial {
     pub offset: f32,
     #[uniform(0)]
     pub selected: f32,
+    #[uniform(0)]
+    pub glyphs: f32,
+    #[uniform(0)]
+    pub pulse: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub symbol: Handle<Image>,
 }
 
 pub fn update_beam_material(
     query: Query<(&Handle<BeamMaterial>, &BeamColor)>,
     mut a: ResMut<Assets<BeamMaterial>>,
     time: Res<Time>,
-    color: Res<ClosestBeam>,
+    players: Query<&ClosestBeam>,
+    palette: Res<Palette>,
+    pulse: Res<MusicPulse>,
+    modifiers: Res<RunModifiers>,
 ) {
     for (handle, beam) in &query {
         if let Some(mat) = a.get_mut(handle) {
             mat.time = time.seconds_since_startup() as f32;
+            mat.pulse = pulse.0;
+
+            let mut c = palette.beam(*beam);
+            // locked beams fade into the background
+            c.set_a(if modifiers.locked_beam == Some(*beam) {
+                0.2
+            } else {
+                0.5
+            });
+            mat.color = c;
+            // draw the symbols along the beam when colours alone might not be enough
+            mat.glyphs = if *palette == Palette::Normal {
+                0.0
+            } else {
+                1.0
+            };
 
-            mat.selected = (*beam == color.0).then_some(1.0).unwrap_or(0.0);
+            mat.selected = if players.iter().any(|c| c.0 == *beam) {
+                1.0
+            } else {
+                0.0
+            };
         }
     }
 }
@@ -0,0 +1,401 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+/// how close an enemy has to get to the target to hurt it
+const TARGET_RADIUS: f32 = 45.0;
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum Objective {
+    /// kill this many enemies of a colour
+    Kill {
+        colour: Colour,
+        count: usize,
+    },
+    Survive {
+        seconds: f64,
+    },
+    /// keep the enemies away from a target until the time runs out
+    Protect {
+        position: Vec2,
+        health: i8,
+        seconds: f64,
+    },
+}
+
+/// the most health that can be lost and still get two or three stars
+#[derive(Deserialize, Clone, Copy, Default)]
+pub struct StarThresholds {
+    pub two: i8,
+    pub three: i8,
+}
+
+impl StarThresholds {
+    fn stars(&self, health_lost: i8) -> u8 {
+        1 + (health_lost <= self.two) as u8 + (health_lost <= self.three) as u8
+    }
+}
+
+pub fn star_string(stars: u8) -> String {
+    (0..3).map(|i| if i < stars { '★' } else { '☆' }).collect()
+}
+
+pub struct CampaignLevel {
+    /// locale key
+    pub name: String,
+    pub level: Level,
+    pub objective: Objective,
+    pub stars: StarThresholds,
+}
+
+/// the levels of the campaign in order, loaded from a `.campaign` file
+#[derive(TypeUuid)]
+#[uuid = "2c8e4f1a-7b3d-4e5f-a6c9-1d2e3f4a5b6c"]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+/// an entry of the `.campaign` file, which points at a `.level` file
+#[derive(Deserialize)]
+struct CampaignEntry {
+    name: String,
+    level: String,
+    objective: Objective,
+    stars: StarThresholds,
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let entries: Vec<CampaignEntry> = ron::de::from_bytes(bytes)?;
+            let mut levels = vec![];
+            for entry in entries {
+                let level = load_context.read_asset_bytes(&entry.level).await?;
+                levels.push(CampaignLevel {
+                    name: entry.name,
+                    level: ron::de::from_bytes(&level)?,
+                    objective: entry.objective,
+                    stars: entry.stars,
+                });
+            }
+            load_context.set_default_asset(LoadedAsset::new(Campaign { levels }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign"]
+    }
+}
+
+/// the campaign level being played
+#[derive(Default)]
+pub struct CampaignRun {
+    /// index into the campaign's levels
+    pub level: usize,
+    pub objective: Option<Objective>,
+    pub stars: StarThresholds,
+    kills: usize,
+    target_health: i8,
+    /// stars earned, set when the objective is completed
+    pub result: Option<u8>,
+}
+
+impl CampaignRun {
+    pub fn start(&mut self, index: usize, campaign: &Campaign, current: &mut CurrentLevel) {
+        let entry = &campaign.levels[index];
+        current.0 = entry.level.clone();
+        *self = Self {
+            level: index,
+            objective: Some(entry.objective),
+            stars: entry.stars,
+            ..default()
+        };
+    }
+}
+
+/// the thing that has to be protected in `Objective::Protect` levels
+#[derive(Component)]
+pub struct CampaignTarget;
+
+#[derive(Component)]
+pub struct ObjectiveText;
+
+pub fn setup_campaign_run(
+    mut cmd: Commands,
+    mode: Res<GameMode>,
+    mut run: ResMut<CampaignRun>,
+    a: Res<GameAssets>,
+) {
+    if *mode != GameMode::Campaign {
+        return;
+    }
+
+    run.kills = 0;
+    run.result = None;
+    if let Some(Objective::Protect {
+        position, health, ..
+    }) = run.objective
+    {
+        run.target_health = health;
+        cmd.spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.8, 0.8, 1.0),
+                custom_size: Some(Vec2::splat(30.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(1.0))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        })
+        .insert(CampaignTarget)
+        .insert(CleanupGame);
+    }
+
+    cmd.spawn_bundle(
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: a.font.clone(),
+                font_size: 30.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            // under the selected beam, the right side has the colour legend
+            position: UiRect {
+                top: Val::Px(50.0),
+                left: Val::Percent(45.0),
+                ..default()
+            },
+            ..default()
+        }),
+    )
+    .insert(Localized::new("objective-survive"))
+    .insert(ObjectiveText)
+    .insert(CleanupGame);
+}
+
+pub fn count_campaign_kills(mut run: ResMut<CampaignRun>, mut ev: EventReader<EnemyDead>) {
+    for dead in ev.iter() {
+        if let Some(Objective::Kill { colour, .. }) = run.objective {
+            if dead.colour == colour {
+                run.kills += 1;
+            }
+        }
+    }
+}
+
+pub fn damage_campaign_target(
+    mut cmd: Commands,
+    target: Query<&Transform, With<CampaignTarget>>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut run: ResMut<CampaignRun>,
+    mut ev: EventWriter<PlayerHit>,
+) {
+    let target = match target.get_single() {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+    for (entity, trans) in &enemies {
+        if target.translation.xy().distance(trans.translation.xy()) < TARGET_RADIUS {
+            cmd.entity(entity).despawn_recursive();
+            run.target_health -= 1;
+            ev.send(PlayerHit {
+                position: trans.translation.xy(),
+            });
+        }
+    }
+}
+
+pub fn check_objective(
+    mode: Res<GameMode>,
+    mut run: ResMut<CampaignRun>,
+    health: Res<PlayerHealth>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+    mut save: ResMut<SaveData>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let objective = match run.objective {
+        Some(o) if *mode == GameMode::Campaign => o,
+        _ => return,
+    };
+    // dying already changes the state
+    if health.health <= 0 {
+        return;
+    }
+
+    let elapsed = time.seconds_since_startup() - game_start.0;
+    let done = match objective {
+        Objective::Kill { count, .. } => run.kills >= count,
+        Objective::Survive { seconds } => elapsed >= seconds,
+        Objective::Protect { seconds, .. } => {
+            if run.target_health <= 0 {
+                state.set(GameState::Death).unwrap();
+                return;
+            }
+            elapsed >= seconds
+        }
+    };
+
+    if done {
+        let stars = run.stars.stars(health.max - health.health);
+        run.result = Some(stars);
+        save.record_campaign_stars(run.level, stars);
+        save.store();
+        state.set(GameState::Death).unwrap();
+    }
+}
+
+pub fn update_objective_ui(
+    run: Res<CampaignRun>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+    mut text: Query<&mut Localized, With<ObjectiveText>>,
+) {
+    let objective = match run.objective {
+        Some(o) => o,
+        None => return,
+    };
+    let elapsed = ((time.seconds_since_startup() - game_start.0) as u32).to_string();
+
+    for mut text in &mut text {
+        let (key, args) = match objective {
+            Objective::Kill { colour, count } => (
+                "objective-kill",
+                vec![
+                    format!("objective-colour-{}", colour.name()),
+                    run.kills.to_string(),
+                    count.to_string(),
+                ],
+            ),
+            Objective::Survive { seconds } => (
+                "objective-survive",
+                vec![elapsed.clone(), seconds.to_string()],
+            ),
+            Objective::Protect { seconds, .. } => (
+                "objective-protect",
+                vec![
+                    run.target_health.max(0).to_string(),
+                    elapsed.clone(),
+                    seconds.to_string(),
+                ],
+            ),
+        };
+        // only touch the text when it changes, so it doesn't get re-rendered every frame
+        if text.key != key || text.args != args {
+            text.key = key;
+            text.args = args;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LevelSelectButton(usize);
+
+#[derive(Component)]
+pub struct LevelSelectBackButton;
+
+pub fn setup_level_select(
+    mut commands: Commands,
+    a: Res<GameAssets>,
+    level_assets: Res<LevelAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    save: Res<SaveData>,
+) {
+    let campaign = level_assets.campaign(&campaigns);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::SpaceEvenly,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(CleanupLevelSelect)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: a.font.clone(),
+                        font_size: 60.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(Localized::new("level-select-title"));
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (i, level) in campaign.into_iter().flat_map(|c| &c.levels).enumerate() {
+                        let label = if save.is_campaign_unlocked(i) {
+                            Localized::with_args(
+                                "level-select-entry",
+                                vec![level.name.clone(), star_string(save.level_stars(i))],
+                            )
+                        } else {
+                            Localized::with_args("level-select-locked", vec![level.name.clone()])
+                        };
+                        spawn_button(parent, &a, label, 30.0).insert(LevelSelectButton(i));
+                    }
+                });
+
+            spawn_button(parent, &a, Localized::new("level-select-back"), 30.0)
+                .insert(LevelSelectBackButton);
+        });
+}
+
+pub fn level_select(
+    mut state: ResMut<State<GameState>>,
+    mut mode: ResMut<GameMode>,
+    mut run: ResMut<CampaignRun>,
+    mut current: ResMut<CurrentLevel>,
+    save: Res<SaveData>,
+    level_assets: Res<LevelAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    levels: Query<(&Interaction, &LevelSelectButton), Changed<Interaction>>,
+    back: Query<&Interaction, (Changed<Interaction>, With<LevelSelectBackButton>)>,
+) {
+    for (interaction, button) in &levels {
+        if *interaction != Interaction::Clicked || !save.is_campaign_unlocked(button.0) {
+            continue;
+        }
+        if let Some(campaign) = level_assets.campaign(&campaigns) {
+            run.start(button.0, campaign, &mut current);
+            *mode = GameMode::Campaign;
+            state.set(GameState::Game).unwrap();
+        }
+    }
+    for interaction in &back {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Menu).unwrap();
+        }
+    }
+}
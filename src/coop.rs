@@ -0,0 +1,270 @@
+use crate::*;
+
+/// how fast the second player's aim moves with the keyboard, in px/s
+const AIM_SPEED: f32 = 400.0;
+/// the second player aims at a point this far right
+const AIM_X: f32 = ARENA_WIDTH / 2.0;
+
+/// local co-op options, chosen in the menu
+#[derive(Default)]
+pub struct CoopSettings {
+    pub enabled: bool,
+    /// when off, each player has their own health and the run ends when both are down
+    pub shared_health: bool,
+}
+
+impl CoopSettings {
+    pub fn players(&self) -> usize {
+        if self.enabled {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// health for the whole team at the start of a run
+    pub fn starting_health(&self, per_player: i8) -> i8 {
+        if self.shared_health {
+            per_player
+        } else {
+            per_player.saturating_mul(self.players() as i8)
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    /// wasd to move, the mouse to aim, space to dash and e or right click to shield
+    KeyboardMouse,
+    /// ijkl to move, u/o to aim, h to dash and y to shield, or the first gamepad
+    Second,
+    /// whoever joined over the network, see `RemoteInput`
+    Remote,
+}
+
+impl Controls {
+    pub fn for_player(index: usize, hosting: bool) -> Self {
+        match index {
+            0 => Controls::KeyboardMouse,
+            _ if hosting => Controls::Remote,
+            _ => Controls::Second,
+        }
+    }
+
+    pub fn movement(
+        &self,
+        keys: &Input<KeyCode>,
+        gamepads: &Gamepads,
+        axes: &Axis<GamepadAxis>,
+        remote: &RemoteInput,
+    ) -> Vec2 {
+        let (up, down, left, right) = match self {
+            Controls::KeyboardMouse => (KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D),
+            Controls::Second => (KeyCode::I, KeyCode::K, KeyCode::J, KeyCode::L),
+            Controls::Remote => return remote.movement,
+        };
+
+        let mut diff = Vec2::ZERO;
+        if keys.pressed(up) {
+            diff += Vec2::Y;
+        }
+        if keys.pressed(down) {
+            diff -= Vec2::Y;
+        }
+        if keys.pressed(left) {
+            diff -= Vec2::X;
+        }
+        if keys.pressed(right) {
+            diff += Vec2::X;
+        }
+        if *self == Controls::Second {
+            if let Some(pad) = gamepads.iter().next() {
+                diff += stick(
+                    axes,
+                    *pad,
+                    GamepadAxisType::LeftStickX,
+                    GamepadAxisType::LeftStickY,
+                );
+            }
+        }
+        diff
+    }
+
+    /// whether the dash button was pressed this frame, holding it down does nothing
+    pub fn dash(
+        &self,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+        gamepads: &Gamepads,
+        remote: &RemoteInput,
+    ) -> bool {
+        match self {
+            Controls::KeyboardMouse => keys.just_pressed(KeyCode::Space),
+            Controls::Second => {
+                keys.just_pressed(KeyCode::H)
+                    || gamepads.iter().next().is_some_and(|pad| {
+                        buttons.just_pressed(GamepadButton::new(*pad, GamepadButtonType::South))
+                    })
+            }
+            Controls::Remote => remote.dash_pressed,
+        }
+    }
+
+    /// whether the dash button is down, what the client sends to the host
+    pub fn dash_held(
+        &self,
+        keys: &Input<KeyCode>,
+        buttons: &Input<GamepadButton>,
+        gamepads: &Gamepads,
+        remote: &RemoteInput,
+    ) -> bool {
+        match self {
+            Controls::KeyboardMouse => keys.pressed(KeyCode::Space),
+            Controls::Second => {
+                keys.pressed(KeyCode::H)
+                    || gamepads.iter().next().is_some_and(|pad| {
+                        buttons.pressed(GamepadButton::new(*pad, GamepadButtonType::South))
+                    })
+            }
+            Controls::Remote => remote.dash,
+        }
+    }
+
+    /// whether the shield button was pressed this frame, holding it down does nothing
+    pub fn shield(
+        &self,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        buttons: &Input<GamepadButton>,
+        gamepads: &Gamepads,
+        remote: &RemoteInput,
+    ) -> bool {
+        match self {
+            Controls::KeyboardMouse => {
+                keys.just_pressed(KeyCode::E) || mouse.just_pressed(MouseButton::Right)
+            }
+            Controls::Second => {
+                keys.just_pressed(KeyCode::Y)
+                    || gamepads.iter().next().is_some_and(|pad| {
+                        buttons.just_pressed(GamepadButton::new(*pad, GamepadButtonType::East))
+                    })
+            }
+            Controls::Remote => remote.shield_pressed,
+        }
+    }
+
+    /// whether the shield button is down, what the client sends to the host
+    pub fn shield_held(
+        &self,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        buttons: &Input<GamepadButton>,
+        gamepads: &Gamepads,
+        remote: &RemoteInput,
+    ) -> bool {
+        match self {
+            Controls::KeyboardMouse => {
+                keys.pressed(KeyCode::E) || mouse.pressed(MouseButton::Right)
+            }
+            Controls::Second => {
+                keys.pressed(KeyCode::Y)
+                    || gamepads.iter().next().is_some_and(|pad| {
+                        buttons.pressed(GamepadButton::new(*pad, GamepadButtonType::East))
+                    })
+            }
+            Controls::Remote => remote.shield,
+        }
+    }
+}
+
+fn stick(axes: &Axis<GamepadAxis>, pad: Gamepad, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+    let get = |axis| axes.get(GamepadAxis::new(pad, axis)).unwrap_or(0.0);
+    Vec2::new(get(x), get(y))
+}
+
+/// the point a player's beam points at
+#[derive(Component, Default)]
+pub struct Aim(pub Vec2);
+
+pub fn update_aim(
+    mut players: Query<(&mut Aim, &Controls)>,
+    mouse: Res<MousePos>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    time: Res<Time>,
+    remote: Res<RemoteInput>,
+) {
+    for (mut aim, controls) in &mut players {
+        match controls {
+            Controls::KeyboardMouse => aim.0 = mouse.pos,
+            Controls::Remote => aim.0 = remote.aim,
+            Controls::Second => {
+                let mut dy = 0.0;
+                if keys.pressed(KeyCode::U) {
+                    dy += 1.0;
+                }
+                if keys.pressed(KeyCode::O) {
+                    dy -= 1.0;
+                }
+                if let Some(pad) = gamepads.iter().next() {
+                    dy += stick(
+                        &axes,
+                        *pad,
+                        GamepadAxisType::RightStickX,
+                        GamepadAxisType::RightStickY,
+                    )
+                    .y;
+                }
+
+                let y = aim.0.y + dy * AIM_SPEED * time.delta_seconds();
+                aim.0 = Vec2::new(AIM_X, y.clamp(-ARENA_HEIGHT / 2.0, ARENA_HEIGHT / 2.0));
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct CoopButton;
+
+#[derive(Component)]
+pub struct SharedHealthButton;
+
+fn on_off(on: bool) -> String {
+    if on { "menu-on" } else { "menu-off" }.to_string()
+}
+
+pub fn coop_button_labels(coop: &CoopSettings) -> (Localized, Localized) {
+    (
+        Localized::with_args("menu-coop", vec![on_off(coop.enabled)]),
+        Localized::with_args("menu-shared-health", vec![on_off(coop.shared_health)]),
+    )
+}
+
+pub fn coop_buttons(
+    mut coop: ResMut<CoopSettings>,
+    enabled: Query<(&Interaction, &Children), (Changed<Interaction>, With<CoopButton>)>,
+    shared: Query<(&Interaction, &Children), (Changed<Interaction>, With<SharedHealthButton>)>,
+    mut texts: Query<&mut Localized>,
+) {
+    for (interaction, children) in &enabled {
+        if *interaction == Interaction::Clicked {
+            coop.enabled = !coop.enabled;
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.args = vec![on_off(coop.enabled)];
+                }
+            }
+        }
+    }
+    for (interaction, children) in &shared {
+        if *interaction == Interaction::Clicked {
+            coop.shared_health = !coop.shared_health;
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.args = vec![on_off(coop.shared_health)];
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::*;
+use rand::{rngs::StdRng, seq::index::sample, seq::SliceRandom, SeedableRng};
+
+/// how many of the modifiers are on each day
+const DAILY_MODIFIER_COUNT: usize = 2;
+
+/// days since the unix epoch, in utc, so everyone gets the same challenge
+#[cfg(not(target_arch = "wasm32"))]
+pub fn today() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn today() -> u64 {
+    (js_sys::Date::now() / 86_400_000.0) as u64
+}
+
+/// the day the daily run started on, so a run that goes past midnight still counts for it
+#[derive(Default)]
+pub struct DailyDay(pub u64);
+
+/// the modifiers for a day's challenge
+pub fn daily_modifiers(day: u64) -> RunModifiers {
+    // a different stream than the one the run itself uses
+    let mut rng = StdRng::seed_from_u64(day ^ 0x5eed_da11);
+    let mut modifiers = RunModifiers::default();
+    for i in sample(&mut rng, 4, DAILY_MODIFIER_COUNT) {
+        match i {
+            0 => modifiers.enemy_speed = 1.5,
+            1 => modifiers.only_mixed = true,
+            2 => {
+                modifiers.locked_beam = [BeamColor::Red, BeamColor::Green, BeamColor::Blue]
+                    .choose(&mut rng)
+                    .copied()
+            }
+            _ => modifiers.spawn_rate = 2.0,
+        }
+    }
+    modifiers
+}
+
+pub fn record_daily_score(
+    mode: Res<GameMode>,
+    score: Res<EnemiesKilled>,
+    modifiers: Res<RunModifiers>,
+    day: Res<DailyDay>,
+    mut save: ResMut<SaveData>,
+) {
+    if *mode == GameMode::Daily {
+        save.add_daily_score(day.0, modifiers.score(score.0));
+        save.store();
+    }
+}
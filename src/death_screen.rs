@@ -1,6 +1,28 @@
 use crate::*;
 
-pub fn setup_death_screen(mut commands: Commands, a: Res<GameAssets>, score: Res<EnemiesKilled>) {
+pub fn setup_death_screen(
+    mut commands: Commands,
+    a: Res<GameAssets>,
+    score: Res<EnemiesKilled>,
+    mode: Res<GameMode>,
+    rhythm: Res<RhythmScore>,
+    save: Res<SaveData>,
+    test: Res<TestPlay>,
+    run: Res<CampaignRun>,
+    level_assets: Res<LevelAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    modifiers: Res<RunModifiers>,
+) {
+    let campaign = *mode == GameMode::Campaign;
+    let (title, title_color) = match run.result {
+        Some(_) if campaign => ("campaign-complete", Color::GREEN),
+        None if campaign => ("campaign-failed", Color::RED),
+        _ => ("death-title", Color::RED),
+    };
+    let has_next_level = level_assets
+        .campaign(&campaigns)
+        .is_some_and(|c| run.level + 1 < c.levels.len());
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -15,63 +37,209 @@ pub fn setup_death_screen(mut commands: Commands, a: Res<GameAssets>, score: Res
         })
         .insert(CleanupDeath)
         .with_children(|parent| {
-            parent.spawn_bundle(
-                TextBundle::from_section(
-                    "you died!",
-                    TextStyle {
-                        font: a.font.clone(),
-                        font_size: 80.0,
-                        color: Color::RED,
-                    },
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: a.font.clone(),
+                            font_size: 80.0,
+                            color: title_color,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
                 )
-                .with_text_alignment(TextAlignment::TOP_CENTER),
-            );
+                .insert(Localized::new(title));
 
-            parent.spawn_bundle(
-                TextBundle::from_section(
-                    &format!("Points: {}", score.0),
-                    TextStyle {
-                        font: a.font.clone(),
-                        font_size: 60.0,
-                        color: Color::WHITE,
-                    },
-                )
-                .with_text_alignment(TextAlignment::TOP_CENTER),
-            );
+            if let Some(stars) = run.result.filter(|_| campaign) {
+                parent
+                    .spawn_bundle(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: a.font.clone(),
+                            font_size: 60.0,
+                            color: Color::rgb(1.0, 0.9, 0.3),
+                        },
+                    ))
+                    .insert(Localized::with_args(
+                        "campaign-stars",
+                        vec![star_string(stars)],
+                    ));
+            }
 
             parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..default()
-                    },
-                    color: NORMAL_BUTTON.into(),
-                    ..default()
-                })
-                .insert(PlayAgainButton)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle::from_section(
-                        "Play again!",
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: a.font.clone(),
+                            font_size: 60.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                )
+                .insert(Localized::with_args(
+                    "death-points",
+                    vec![modifiers.score(score.0).to_string()],
+                ));
+
+            if !test.0 {
+                parent
+                    .spawn_bundle(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: a.font.clone(),
+                            font_size: 30.0,
+                            color: Color::rgb(0.6, 0.9, 1.0),
+                        },
+                    ))
+                    .insert(Localized::with_args(
+                        "death-shards",
+                        vec![score.0.to_string(), save.shards.to_string()],
+                    ));
+            }
+
+            if let Some(best) = save.daily_best.filter(|_| *mode == GameMode::Daily) {
+                parent
+                    .spawn_bundle(TextBundle::from_section(
+                        "",
                         TextStyle {
                             font: a.font.clone(),
                             font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+                            color: Color::WHITE,
                         },
+                    ))
+                    .insert(Localized::with_args(
+                        "death-daily-best",
+                        vec![best.score.to_string()],
                     ));
-                });
+            }
+
+            if *mode == GameMode::Rhythm {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::ColumnReverse,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        let text = |size| {
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: a.font.clone(),
+                                    font_size: size,
+                                    color: Color::WHITE,
+                                },
+                            )
+                        };
+                        parent.spawn_bundle(text(40.0)).insert(Localized::with_args(
+                            "death-rhythm-score",
+                            vec![rhythm.score.to_string()],
+                        ));
+                        parent
+                            .spawn_bundle(text(30.0))
+                            .insert(Localized::new("death-leaderboard"));
+                        for (i, score) in save.rhythm_leaderboard.iter().enumerate() {
+                            parent.spawn_bundle(text(25.0)).insert(Localized::with_args(
+                                "leaderboard-entry",
+                                vec![(i + 1).to_string(), score.to_string()],
+                            ));
+                        }
+                    });
+            }
+
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                spawn_button(parent, &a, Localized::new("death-play-again"), 40.0)
+                    .insert(PlayAgainButton);
+                if campaign && run.result.is_some() && has_next_level {
+                    spawn_button(parent, &a, Localized::new("campaign-next"), 40.0)
+                        .insert(NextLevelButton);
+                }
+                if campaign {
+                    spawn_button(parent, &a, Localized::new("campaign-level-select"), 40.0)
+                        .insert(LevelSelectMenuButton);
+                } else if !test.0 {
+                    spawn_button(parent, &a, Localized::new("death-menu"), 40.0)
+                        .insert(DeathMenuButton);
+                }
+                if test.0 {
+                    spawn_button(parent, &a, Localized::new("death-back-to-editor"), 40.0)
+                        .insert(BackToEditorButton);
+                }
+            });
         });
 }
 
 #[derive(Component)]
 pub struct PlayAgainButton;
 
+#[derive(Component)]
+pub struct BackToEditorButton;
+
+#[derive(Component)]
+pub struct NextLevelButton;
+
+#[derive(Component)]
+pub struct LevelSelectMenuButton;
+
+#[derive(Component)]
+pub struct DeathMenuButton;
+
 pub fn death_screen(
     mut state: ResMut<State<GameState>>,
     mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<PlayAgainButton>)>,
+    editor: Query<&Interaction, (Changed<Interaction>, With<BackToEditorButton>)>,
+    next: Query<&Interaction, (Changed<Interaction>, With<NextLevelButton>)>,
+    level_select: Query<&Interaction, (Changed<Interaction>, With<LevelSelectMenuButton>)>,
+    menu: Query<&Interaction, (Changed<Interaction>, With<DeathMenuButton>)>,
+    mut run: ResMut<CampaignRun>,
+    mut current: ResMut<CurrentLevel>,
+    level_assets: Res<LevelAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    mode: Res<GameMode>,
+    test: Res<TestPlay>,
 ) {
     for interaction in &mut interaction_query {
         if *interaction == Interaction::Clicked {
-            state.set(GameState::Game).unwrap();
+            // upgrades only apply to endless and rhythm runs
+            let shop = matches!(*mode, GameMode::Endless | GameMode::Rhythm) && !test.0;
+            state
+                .set(if shop {
+                    GameState::Shop
+                } else {
+                    GameState::Game
+                })
+                .unwrap();
+        }
+    }
+    for interaction in &editor {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Editor).unwrap();
+        }
+    }
+    for interaction in &next {
+        if *interaction == Interaction::Clicked {
+            if let Some(campaign) = level_assets.campaign(&campaigns) {
+                let next = run.level + 1;
+                run.start(next, campaign, &mut current);
+                state.set(GameState::Game).unwrap();
+            }
+        }
+    }
+    for interaction in &level_select {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::LevelSelect).unwrap();
+        }
+    }
+    for interaction in &menu {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Menu).unwrap();
         }
     }
 }
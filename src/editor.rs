@@ -0,0 +1,423 @@
+use crate::*;
+use bevy::asset::LoadState;
+
+/// where the editor saves and loads, relative to the assets folder
+const CUSTOM_LEVEL_PATH: &str = "levels/custom.level";
+
+const PIVOT_GRAB_RADIUS: f32 = 20.0;
+const LANE_GRAB_DISTANCE: f32 = 15.0;
+const OBSTACLE_SIZE: Vec2 = Vec2::new(40.0, 80.0);
+
+// timeline along the bottom of the screen
+const TIMELINE_Y: f32 = -ARENA_HEIGHT / 2.0 + 40.0;
+const TIMELINE_LEFT: f32 = -ARENA_WIDTH / 2.0 + 40.0;
+const TIMELINE_WIDTH: f32 = ARENA_WIDTH - 80.0;
+const PIXELS_PER_SECOND: f32 = 40.0;
+const TIMELINE_STEP: f64 = 0.5;
+
+const SPAWN_KEYS: [KeyCode; 7] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+];
+
+#[derive(Default)]
+pub struct EditorState {
+    pub level: Level,
+    /// the level is only copied from the arena the first time the editor opens
+    opened: bool,
+    /// index of the beam being dragged
+    dragging: Option<usize>,
+    /// corner where the player bounds started being dragged from
+    bounds_from: Option<Vec2>,
+    /// where new spawns go in the timeline
+    cursor_time: f64,
+    loading: Option<Handle<Level>>,
+}
+
+/// whether the current run was started from the editor
+#[derive(Default)]
+pub struct TestPlay(pub bool);
+
+#[derive(Component, Clone, Copy)]
+pub enum EditorButton {
+    Save,
+    Load,
+    Test,
+    Back,
+}
+
+#[derive(Component)]
+pub struct EditorVisual;
+
+#[derive(Component)]
+pub struct EditorTimeText;
+
+pub fn setup_editor(
+    mut commands: Commands,
+    a: Res<GameAssets>,
+    mut editor: ResMut<EditorState>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<Level>>,
+) {
+    if !editor.opened {
+        editor.level = level_assets.arena(&levels);
+        editor.opened = true;
+    }
+    // so the level gets drawn
+    editor.set_changed();
+
+    let style = |size| TextStyle {
+        font: a.font.clone(),
+        font_size: size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(CleanupEditor)
+        .with_children(|parent| {
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                for (key, button) in [
+                    ("editor-save", EditorButton::Save),
+                    ("editor-load", EditorButton::Load),
+                    ("editor-test", EditorButton::Test),
+                    ("editor-back", EditorButton::Back),
+                ] {
+                    spawn_button(parent, &a, Localized::new(key), 25.0).insert(button);
+                }
+            });
+
+            parent
+                .spawn_bundle(TextBundle::from_section("", style(20.0)))
+                .insert(Localized::with_args("editor-time", vec!["0".to_string()]))
+                .insert(EditorTimeText);
+
+            for key in [
+                "editor-help-pivots",
+                "editor-help-bounds",
+                "editor-help-lanes",
+                "editor-help-obstacles",
+                "editor-help-spawns",
+                "editor-help-timeline",
+                "editor-help-delete",
+                "editor-help-test",
+            ] {
+                parent
+                    .spawn_bundle(TextBundle::from_section("", style(14.0)))
+                    .insert(Localized::new(key));
+            }
+        });
+}
+
+fn nearest_lane(level: &Level, y: f32) -> Option<usize> {
+    level
+        .lanes
+        .iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - y).abs().total_cmp(&(b.1 - y).abs()))
+        .map(|(i, _)| i)
+}
+
+pub fn editor_input(
+    mut editor: ResMut<EditorState>,
+    mouse: Res<MousePos>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    ui: Query<&Interaction, With<Button>>,
+) {
+    let pos = mouse.pos;
+    // clicks on the buttons shouldn't also edit the level
+    let over_ui = ui.iter().any(|i| *i != Interaction::None);
+
+    // pivots
+    if buttons.just_pressed(MouseButton::Left) && !over_ui {
+        editor.dragging = editor
+            .level
+            .beams
+            .iter()
+            .position(|b| b.pivot.distance(pos) < PIVOT_GRAB_RADIUS);
+    }
+    if buttons.just_released(MouseButton::Left) && editor.dragging.is_some() {
+        editor.dragging = None;
+    }
+    if let Some(i) = editor.dragging {
+        editor.level.beams[i].pivot = pos;
+    }
+
+    // player bounds
+    if buttons.just_pressed(MouseButton::Right) && !over_ui {
+        editor.bounds_from = Some(pos);
+    }
+    if buttons.just_released(MouseButton::Right) {
+        if let Some(from) = editor.bounds_from.take() {
+            editor.level.player_min = from.min(pos);
+            editor.level.player_max = from.max(pos);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::L) {
+        editor.level.lanes.push(pos.y);
+    }
+    if keys.just_pressed(KeyCode::O) {
+        editor.level.obstacles.push(Obstacle {
+            position: pos,
+            size: OBSTACLE_SIZE,
+        });
+    }
+
+    // spawns
+    for (key, colour) in SPAWN_KEYS.into_iter().zip(ALL_COLORS) {
+        if keys.just_pressed(key) {
+            if let Some(lane) = nearest_lane(&editor.level, pos.y) {
+                let time = editor.cursor_time;
+                editor
+                    .level
+                    .spawns
+                    .push(ScriptedSpawn { time, lane, colour });
+            }
+        }
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        editor.cursor_time = (editor.cursor_time - TIMELINE_STEP).max(0.0);
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        editor.cursor_time += TIMELINE_STEP;
+    }
+
+    if keys.just_pressed(KeyCode::Delete) || keys.just_pressed(KeyCode::Back) {
+        let cursor_time = editor.cursor_time;
+        let lane = nearest_lane(&editor.level, pos.y);
+        let level = &mut editor.level;
+
+        if let Some(i) = level.obstacles.iter().position(|o| o.contains(pos)) {
+            level.obstacles.remove(i);
+        } else if let Some(i) = level.spawns.iter().position(|s| {
+            (s.time - cursor_time).abs() < TIMELINE_STEP / 2.0 && Some(s.lane) == lane
+        }) {
+            level.spawns.remove(i);
+        } else if let Some(lane) = lane.filter(|l| {
+            level.lanes.len() > 1 && (level.lanes[*l] - pos.y).abs() < LANE_GRAB_DISTANCE
+        }) {
+            level.lanes.remove(lane);
+            // spawns in the removed lane go with it, the rest keep their lane
+            level.spawns.retain(|s| s.lane != lane);
+            for spawn in &mut level.spawns {
+                if spawn.lane > lane {
+                    spawn.lane -= 1;
+                }
+            }
+        }
+    }
+}
+
+pub fn editor_buttons(
+    mut state: ResMut<State<GameState>>,
+    interactions: Query<(&Interaction, &EditorButton), Changed<Interaction>>,
+    mut editor: ResMut<EditorState>,
+    mut current: ResMut<CurrentLevel>,
+    mut test: ResMut<TestPlay>,
+    mut mode: ResMut<GameMode>,
+    asset_server: Res<AssetServer>,
+) {
+    for (interaction, button) in &interactions {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            EditorButton::Save => save_level(&editor.level),
+            EditorButton::Load => editor.loading = Some(asset_server.load(CUSTOM_LEVEL_PATH)),
+            EditorButton::Test => {
+                current.0 = editor.level.clone();
+                test.0 = true;
+                *mode = GameMode::Endless;
+                state.set(GameState::Game).unwrap();
+            }
+            EditorButton::Back => state.set(GameState::Menu).unwrap(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_level(level: &Level) {
+    let path = bevy::asset::FileAssetIo::get_base_path()
+        .join("assets")
+        .join(CUSTOM_LEVEL_PATH);
+    let result = level.to_ron().map_err(|e| e.to_string()).and_then(|s| {
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, s))
+            .map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(()) => info!("saved level to {}", path.display()),
+        Err(e) => warn!("couldn't save level to {}: {e}", path.display()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_level(_level: &Level) {
+    warn!("saving levels isn't supported on the web");
+}
+
+pub fn finish_loading_level(
+    mut editor: ResMut<EditorState>,
+    levels: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+) {
+    let handle = match &editor.loading {
+        Some(handle) => handle.clone(),
+        None => return,
+    };
+
+    match asset_server.get_load_state(&handle) {
+        LoadState::Loaded => {
+            if let Some(level) = levels.get(&handle) {
+                editor.level = level.clone();
+                editor.cursor_time = 0.0;
+            }
+            editor.loading = None;
+        }
+        LoadState::Failed => {
+            warn!("couldn't load {CUSTOM_LEVEL_PATH}");
+            editor.loading = None;
+        }
+        _ => {}
+    }
+}
+
+pub fn redraw_editor(
+    mut commands: Commands,
+    editor: Res<EditorState>,
+    palette: Res<Palette>,
+    visuals: Query<Entity, With<EditorVisual>>,
+    mut time_text: Query<&mut Localized, With<EditorTimeText>>,
+) {
+    if !editor.is_changed() && !palette.is_changed() {
+        return;
+    }
+
+    for entity in &visuals {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut text in &mut time_text {
+        text.args = vec![format!("{:.1}", editor.cursor_time)];
+    }
+
+    let level = &editor.level;
+    let mut spawn = |color: Color, size: Vec2, position: Vec3| {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(position),
+                ..default()
+            })
+            .insert(EditorVisual)
+            .insert(CleanupEditor);
+    };
+
+    // player bounds
+    spawn(
+        Color::rgba(1.0, 1.0, 1.0, 0.08),
+        level.player_max - level.player_min,
+        ((level.player_min + level.player_max) / 2.0).extend(0.5),
+    );
+
+    for lane in &level.lanes {
+        spawn(
+            Color::rgba(1.0, 1.0, 1.0, 0.15),
+            Vec2::new(ARENA_WIDTH, 2.0),
+            Vec3::new(0.0, *lane, 0.2),
+        );
+    }
+
+    for beam in &level.beams {
+        spawn(
+            palette.beam(beam.color),
+            Vec2::splat(16.0),
+            beam.pivot.extend(2.0),
+        );
+    }
+
+    // the enemies that come in at the cursor
+    for s in &level.spawns {
+        if (s.time - editor.cursor_time).abs() < TIMELINE_STEP / 2.0 {
+            spawn(
+                palette.colour(s.colour),
+                Vec2::splat(30.0),
+                Vec3::new(ARENA_WIDTH / 2.0 - 30.0, level.lane(s.lane), 2.0),
+            );
+        }
+    }
+
+    // timeline
+    let view_start = (editor.cursor_time - 5.0).max(0.0);
+    let view_end = view_start + (TIMELINE_WIDTH / PIXELS_PER_SECOND) as f64;
+    let time_x = |t: f64| TIMELINE_LEFT + (t - view_start) as f32 * PIXELS_PER_SECOND;
+    spawn(
+        Color::rgba(1.0, 1.0, 1.0, 0.05),
+        Vec2::new(TIMELINE_WIDTH, 50.0),
+        Vec3::new(0.0, TIMELINE_Y, 4.0),
+    );
+    spawn(
+        Color::WHITE,
+        Vec2::new(2.0, 50.0),
+        Vec3::new(time_x(editor.cursor_time), TIMELINE_Y, 4.2),
+    );
+    let lanes = level.lanes.len().max(1) as f32;
+    for s in &level.spawns {
+        if s.time < view_start || s.time > view_end {
+            continue;
+        }
+        // one row per lane, top lane first
+        let row = (lanes - 1.0) / 2.0 - s.lane as f32;
+        spawn(
+            palette.colour(s.colour),
+            Vec2::splat(8.0),
+            Vec3::new(time_x(s.time), TIMELINE_Y + row * 40.0 / lanes, 4.1),
+        );
+    }
+
+    for obstacle in &level.obstacles {
+        commands
+            .spawn_bundle(obstacle_sprite(obstacle))
+            .insert(EditorVisual)
+            .insert(CleanupEditor);
+    }
+}
+
+/// escape goes back to the editor when test playing
+pub fn leave_test_play(
+    test: Res<TestPlay>,
+    health: Res<PlayerHealth>,
+    keys: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // dying already changes the state
+    if test.0 && health.health > 0 && keys.just_pressed(KeyCode::Escape) {
+        state.set(GameState::Editor).unwrap();
+    }
+}
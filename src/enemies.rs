@@ -1,90 +1,219 @@
 use bevy::{
+    ecs::system::SystemParam,
     math::Vec3Swizzles,
     prelude::*,
     reflect::TypeUuid,
     render::render_resource::{AsBindGroup, ShaderRef},
     sprite::{Material2d, MaterialMesh2dBundle},
 };
-use rand::{prelude::ThreadRng, seq::SliceRandom, thread_rng};
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng};
 
 use crate::*;
 
 #[derive(Deref, DerefMut)]
 pub struct EnemySpawnerTimer(pub Timer);
-pub fn spawn_enemies(
-    mut cmd: Commands,
-    pivots: Query<&Pivot>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+
+/// how long before an enemy enters the lane is shown on the edge of the arena
+pub const SPAWN_WARNING_SECONDS: f64 = 1.0;
+
+pub struct PlannedSpawn {
+    pub lane: f32,
+    pub colour: Colour,
+    /// time since startup when the enemy should spawn
+    pub at: f64,
+    warning: Entity,
+}
+
+/// enemies that have been decided on but haven't entered the arena yet, in spawn order
+#[derive(Default, Deref, DerefMut)]
+pub struct SpawnQueue(pub VecDeque<PlannedSpawn>);
+
+#[derive(Component)]
+pub struct SpawnWarning;
+
+/// everything needed to put a spawn warning on the edge of the arena
+#[derive(SystemParam)]
+pub struct WarningSpawner<'w, 's> {
+    cmd: Commands<'w, 's>,
+    mats: ResMut<'w, Assets<EnemyMaterial>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    symbols: Res<'w, EnemySymbols>,
+    palette: Res<'w, Palette>,
+    modifiers: Res<'w, RunModifiers>,
+    level: Res<'w, CurrentLevel>,
+}
+
+impl<'w, 's> WarningSpawner<'w, 's> {
+    /// shows the warning right away, the enemy will be spawned by `spawn_enemies` at `at`
+    pub fn plan(&mut self, lane: f32, colour: Colour, at: f64) -> PlannedSpawn {
+        let lane = self.modifiers.lane(lane, &self.level);
+        let warning = self
+            .cmd
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: self.meshes.add(Mesh::from(shape::Quad::default())).into(),
+                transform: Transform::default()
+                    .with_translation(Vec3::new(ARENA_WIDTH / 2.0 - 30.0, lane, 5.0))
+                    .with_scale(Vec3::new(30.0, 30.0, 1.0)),
+                material: self.mats.add(EnemyMaterial {
+                    damaged: 1.0,
+                    ..EnemyMaterial::new(colour, &self.palette, self.symbols.get(colour))
+                }),
+                ..default()
+            })
+            .insert(SpawnWarning)
+            .insert(colour)
+            .insert(CleanupGame)
+            .id();
+
+        PlannedSpawn {
+            lane,
+            colour,
+            at,
+            warning,
+        }
+    }
+}
+
+pub fn plan_enemy_spawns(
+    mode: Res<GameMode>,
+    level: Res<CurrentLevel>,
     mut timer: ResMut<EnemySpawnerTimer>,
+    mut queue: ResMut<SpawnQueue>,
     time: Res<Time>,
     game_start: Res<GameStartTime>,
-    mut mats: ResMut<Assets<EnemyMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    symbols: Res<EnemySymbols>,
-    a: Res<EnemyAssets>,
+    modifiers: Res<RunModifiers>,
+    mut rng: ResMut<GameRng>,
+    mut spawner: WarningSpawner,
 ) {
-    if !timer.tick(time.delta()).just_finished() {
+    if *mode == GameMode::Rhythm || !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    // scripted spawns go first
+    if !level.spawns.is_empty()
+        && time.seconds_since_startup() - game_start.0 < level.last_spawn_time()
+    {
         return;
     }
 
-    let mut rng = thread_rng();
+    // choose lane
+    let lane = match modifiers.lanes(&level).choose(&mut rng.0) {
+        Some(l) => *l,
+        None => return,
+    };
+    // choose color
+    let colour = get_random_colour(
+        &mut rng.0,
+        time.seconds_since_startup() - game_start.0,
+        &modifiers,
+    );
 
-    let (camera, camera_transform) = q_camera.single();
-    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
-    let spawn_x = ndc_to_world.project_point3(Vec3::new(1.0, 0.0, -1.0)).x + 50.0;
+    let planned = spawner.plan(
+        lane,
+        colour,
+        time.seconds_since_startup() + SPAWN_WARNING_SECONDS,
+    );
+    queue.push_back(planned);
+}
 
-    // choose pivot
-    let pivots = pivots.iter().collect::<Vec<_>>();
-    let pivot = Vec2::new(spawn_x, pivots.choose(&mut rng).unwrap().0.y);
-    // choose color
-    let c = get_random_colour(&mut rng, time.seconds_since_startup() - game_start.0);
-
-    let mesh = meshes.add(Mesh::from(shape::Quad::default()));
-    let material = mats.add(EnemyMaterial {
-        color: c.color(),
-        time: 0.0,
-        damaged: 0.0,
-        symbol: match c {
-            Colour::Red => symbols.red.clone(),
-            Colour::Green => symbols.green.clone(),
-            Colour::Blue => symbols.blue.clone(),
-            Colour::Yellow => symbols.yellow.clone(),
-            Colour::Magenta => symbols.magenta.clone(),
-            Colour::Cyan => symbols.cyan.clone(),
-            Colour::White => symbols.white.clone(),
-        },
-    });
-    cmd.spawn_bundle(SpriteSheetBundle {
-        transform: Transform::default()
-            .with_translation(pivot.extend(1.0))
-            .with_scale(Vec3::splat(2.0)),
-        texture_atlas: a.enemy.clone(),
-        ..default()
-    })
-    .insert(Enemy)
-    .insert(Killable {
-        seconds: 0.0,
-        under_damage: false,
-    })
-    .insert(CleanupGame)
-    .insert(c)
-    .insert(AnimationTimer {
-        timer: Timer::from_seconds(0.1, true),
-        paused: false,
-    })
-    .with_children(|parent| {
-        parent.spawn_bundle(MaterialMesh2dBundle {
-            mesh: mesh.into(),
-            transform: Transform::default()
-                .with_translation(Vec3::new(0.0, 0.0, 2.0))
-                .with_scale(Vec3::new(15., 15.0, 1.0)),
-            material,
-            ..default()
+pub fn update_spawn_warnings(
+    query: Query<(&Handle<EnemyMaterial>, &Colour), With<SpawnWarning>>,
+    mut a: ResMut<Assets<EnemyMaterial>>,
+    time: Res<Time>,
+    palette: Res<Palette>,
+) {
+    for (handle, colour) in &query {
+        if let Some(mat) = a.get_mut(handle) {
+            // flicker faster than damaged enemies do
+            mat.time = time.seconds_since_startup() as f32 * 3.0;
+            mat.color = palette.colour(*colour);
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct EnemySpawner<'w, 's> {
+    pub cmd: Commands<'w, 's>,
+    mats: ResMut<'w, Assets<EnemyMaterial>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    symbols: Res<'w, EnemySymbols>,
+    a: Res<'w, EnemyAssets>,
+    animations: Res<'w, AnimationAssets>,
+    palette: Res<'w, Palette>,
+}
+
+impl<'w, 's> EnemySpawner<'w, 's> {
+    pub fn spawn(&mut self, position: Vec2, c: Colour) -> Entity {
+        let mesh = self.meshes.add(Mesh::from(shape::Quad::default()));
+        let material = self
+            .mats
+            .add(EnemyMaterial::new(c, &self.palette, self.symbols.get(c)));
+        self.cmd
+            .spawn_bundle(SpriteSheetBundle {
+                transform: Transform::default()
+                    .with_translation(position.extend(1.0))
+                    .with_scale(Vec3::splat(2.0)),
+                texture_atlas: self.a.enemy.clone(),
+                ..default()
+            })
+            .insert(Enemy)
+            .insert(Killable {
+                seconds: 0.0,
+                under_damage: false,
+                progress: 0.0,
+                hitting: vec![],
+            })
+            .insert(CleanupGame)
+            .insert(c)
+            .insert(Animator::new(self.animations.enemy.clone(), "walk"))
+            .with_children(|parent| {
+                parent.spawn_bundle(MaterialMesh2dBundle {
+                    mesh: mesh.into(),
+                    transform: Transform::default()
+                        .with_translation(Vec3::new(0.0, 0.0, 2.0))
+                        .with_scale(Vec3::new(15., 15.0, 1.0)),
+                    material,
+                    ..default()
+                });
+            })
+            .id()
+    }
+}
+
+pub fn update_enemy_animation(mut enemies: Query<(&mut Animator, &Killable), With<Enemy>>) {
+    for (mut animator, killable) in &mut enemies {
+        animator.play(if killable.under_damage {
+            "hurt"
+        } else {
+            "walk"
         });
-    });
+    }
+}
+
+pub fn spawn_enemies(mut queue: ResMut<SpawnQueue>, time: Res<Time>, mut spawner: EnemySpawner) {
+    while queue
+        .front()
+        .is_some_and(|s| s.at <= time.seconds_since_startup())
+    {
+        let planned = queue.pop_front().unwrap();
+        spawner.cmd.entity(planned.warning).despawn_recursive();
+        spawner.spawn(Vec2::new(SPAWN_X, planned.lane), planned.colour);
+    }
+}
+
+/// waves are 20 seconds long, and each one adds more mixed colours
+pub fn current_wave(time: f64) -> usize {
+    (time / 20.0) as usize + 1
 }
 
-pub fn get_random_colour(rng: &mut ThreadRng, time: f64) -> Colour {
+pub fn get_random_colour(rng: &mut impl Rng, time: f64, modifiers: &RunModifiers) -> Colour {
+    if modifiers.only_mixed {
+        let mixed = [Colour::Yellow, Colour::Magenta, Colour::Cyan, Colour::White];
+        // white only shows up late, like without the modifier
+        let count = if time < 60.0 { 3 } else { 4 };
+        return *mixed[..count].choose(rng).unwrap();
+    }
     if time < 20.0 {
         return *[Colour::Red, Colour::Green, Colour::Blue]
             .choose(rng)
@@ -124,13 +253,29 @@ pub fn move_enemies(
     mut query: Query<&mut Transform, With<Enemy>>,
     time: Res<Time>,
     player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    target: Query<&Transform, (With<CampaignTarget>, Without<Enemy>)>,
+    level: Res<CurrentLevel>,
+    modifiers: Res<RunModifiers>,
 ) {
-    let dt = time.delta_seconds();
-    let player = player.single();
+    let dt = time.delta_seconds() * modifiers.enemy_speed;
+    // enemies go for the target instead of the player when there is one
+    let target = target.get_single().ok();
+    let home_x = level.home_x();
     for mut trans in &mut query {
-        if trans.translation.x > -500.0 {
+        let pos = trans.translation.xy();
+        // otherwise they go for the closest player
+        let goal = target.or_else(|| {
+            player.iter().min_by(|a, b| {
+                a.translation
+                    .xy()
+                    .distance(pos)
+                    .total_cmp(&b.translation.xy().distance(pos))
+            })
+        });
+
+        if trans.translation.x > home_x {
             trans.translation.x -= dt * 50.0;
-        } else if let Some(v) = (player.translation.xy() - trans.translation.xy()).try_normalize() {
+        } else if let Some(v) = goal.and_then(|g| (g.translation.xy() - pos).try_normalize()) {
             let v = v * 50.0 * dt;
             trans.translation.x += v.x;
             trans.translation.y += v.y;
@@ -139,7 +284,10 @@ pub fn move_enemies(
 }
 
 pub struct EnemiesKilled(pub usize);
-pub struct EnemyDead;
+pub struct EnemyDead {
+    pub colour: Colour,
+    pub position: Vec2,
+}
 
 pub fn damage_enemies(
     mut cmd: Commands,
@@ -148,8 +296,10 @@ pub fn damage_enemies(
     time: Res<Time>,
     mut killed: ResMut<EnemiesKilled>,
     mut ev: EventWriter<EnemyDead>,
+    level: Res<CurrentLevel>,
+    modifiers: Res<RunModifiers>,
 ) {
-    'ent: for (entity, trans, colour, mut killable) in &mut killable {
+    for (entity, trans, colour, mut killable) in &mut killable {
         // get the beams currently hitting the enemy
         let mut hitting_colors = vec![];
         for (beam_trans, pivot, color) in &beams {
@@ -157,30 +307,88 @@ pub fn damage_enemies(
                 beam_trans.translation.xy(),
                 pivot.0,
                 trans.translation.xy(),
-                ENEMY_RADIUS,
-            ) && trans.translation.xy().distance(pivot.0) < BEAM_LENGTH + ENEMY_RADIUS / 2.0
+                modifiers.beam_reach(),
+            ) && trans.translation.xy().distance(pivot.0)
+                < modifiers.scaled_beam_length() + ENEMY_RADIUS / 2.0
+                && !level
+                    .obstacles
+                    .iter()
+                    .any(|o| o.blocks(pivot.0, trans.translation.xy()))
             {
                 hitting_colors.push(*color);
             }
         }
 
-        killable.under_damage = false;
+        let required = colour.made_by();
+        killable.hitting = required
+            .iter()
+            .copied()
+            .filter(|c| hitting_colors.contains(c))
+            .collect();
 
-        // if any of the required colors is not hitting, exit
-        for c in colour.made_by() {
-            if !hitting_colors.contains(&c) {
-                continue 'ent;
-            }
+        killable.under_damage = killable.hitting.len() == required.len();
+        let rate = if killable.under_damage {
+            1.0
+        } else if modifiers.partial_damage {
+            killable.hitting.len() as f32 / required.len() as f32
+        } else {
+            0.0
+        };
+
+        let dt = time.delta_seconds();
+        killable.seconds = if rate > 0.0 {
+            killable.seconds + dt * rate
+        } else if killable.hitting.is_empty() {
+            // it heals when none of its beams hit it
+            (killable.seconds - dt * modifiers.kill_decay(&level)).max(0.0)
+        } else {
+            // some of them still hold it where it is
+            killable.seconds
+        };
+        let kill_time = modifiers.kill_time(*colour, &level);
+        killable.progress = (killable.seconds / kill_time).min(1.0);
+
+        if killable.seconds > kill_time {
+            start_dying(&mut cmd, entity);
+            killed.0 += 1;
+            ev.send(EnemyDead {
+                colour: *colour,
+                position: trans.translation.xy(),
+            });
         }
+    }
+}
+
+/// how long the dissolve takes before a dead enemy is despawned, in seconds
+pub const DYING_SECONDS: f32 = 0.4;
+
+/// an enemy that was killed and is dissolving away.
+/// it isn't an `Enemy` anymore, so it doesn't move or hurt anyone
+#[derive(Component)]
+pub struct Dying(pub Timer);
 
-        killable.under_damage = true;
-        killable.seconds += time.delta_seconds();
+pub fn start_dying(cmd: &mut Commands, entity: Entity) {
+    cmd.entity(entity)
+        .remove::<Enemy>()
+        .remove::<Killable>()
+        .insert(Dying(Timer::from_seconds(DYING_SECONDS, false)));
+}
 
-        if killable.seconds > 2.0 {
+pub fn dissolve_dying_enemies(
+    mut cmd: Commands,
+    mut query: Query<(Entity, &mut Dying, &mut Transform, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut dying, mut trans, mut sprite) in &mut query {
+        dying.0.tick(time.delta());
+        if dying.0.finished() {
             cmd.entity(entity).despawn_recursive();
-            killed.0 += 1;
-            ev.send(EnemyDead);
+            continue;
         }
+        // the body swells and fades while the core shatters
+        let progress = dying.0.percent();
+        trans.scale = Vec3::splat(2.0 * (1.0 + progress * 0.5));
+        sprite.color.set_a(1.0 - progress);
     }
 }
 
@@ -192,17 +400,69 @@ fn is_intersect(line_a: Vec2, line_b: Vec2, circle_center: Vec2, circle_radius:
     distance < circle_radius
 }
 
+pub struct PlayerHit {
+    /// where the enemy that hit the player was
+    pub position: Vec2,
+}
+
 pub fn damage_player(
     mut cmd: Commands,
-    player: Query<&Transform, With<Player>>,
-    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&mut Health>,
+            Option<&Invulnerable>,
+            &Shield,
+        ),
+        With<Player>,
+    >,
+    mut enemies: Query<(Entity, &mut Transform), (With<Enemy>, Without<Player>)>,
     mut health: ResMut<PlayerHealth>,
+    mut ev: EventWriter<PlayerHit>,
+    config: Res<MovementConfig>,
 ) {
-    let player = player.single();
-    for (entity, trans) in &enemies {
-        if player.translation.xy().distance(trans.translation.xy()) < 55.0 {
+    let mut hit = vec![];
+    for (player_entity, player, mut own, invulnerable, shield) in &mut players {
+        let mut was_hit = false;
+        for (entity, mut trans) in &mut enemies {
+            // an enemy can only hit one player
+            if hit.contains(&entity)
+                || player.translation.xy().distance(trans.translation.xy()) >= 55.0
+            {
+                continue;
+            }
+            hit.push(entity);
+
+            if shield.is_up() {
+                Shield::parry(player.translation.xy(), &mut trans);
+                continue;
+            }
+
             cmd.entity(entity).despawn_recursive();
+            // enemies still break against an invulnerable player, so they don't pile up on it
+            if invulnerable.is_some() || was_hit {
+                continue;
+            }
+            was_hit = true;
             health.health -= 1;
+            ev.send(PlayerHit {
+                position: trans.translation.xy(),
+            });
+
+            // players that don't share health are out when theirs runs out
+            if let Some(own) = &mut own {
+                own.0 -= 1;
+                if own.0 == 0 {
+                    cmd.entity(player_entity).despawn_recursive();
+                    continue;
+                }
+            }
+            cmd.entity(player_entity)
+                .insert(Invulnerable(Timer::from_seconds(
+                    config.invulnerability,
+                    false,
+                )));
         }
     }
 }
@@ -222,70 +482,159 @@ pub struct EnemyMaterial {
     pub time: f32,
     #[uniform(0)]
     pub damaged: f32,
+    #[uniform(0)]
+    pub pulse: f32,
+    /// 0 while alive, goes up to 1 as the enemy dies
+    #[uniform(0)]
+    pub dissolve: f32,
+    /// how much of the kill ring is filled
+    #[uniform(0)]
+    pub progress: f32,
+    /// one per beam, in red green blue order.
+    /// 0 when the enemy doesn't need it, 0.5 when it's missing and 1 when it's hitting
+    #[uniform(0)]
+    pub beams: Vec4,
+    #[uniform(0)]
+    pub red: Color,
+    #[uniform(0)]
+    pub green: Color,
+    #[uniform(0)]
+    pub blue: Color,
     #[texture(1)]
     #[sampler(2)]
     symbol: Handle<Image>,
 }
 
+impl EnemyMaterial {
+    pub fn new(colour: Colour, palette: &Palette, symbol: Handle<Image>) -> Self {
+        Self {
+            color: palette.colour(colour),
+            time: 0.0,
+            damaged: 0.0,
+            pulse: 0.0,
+            dissolve: 0.0,
+            progress: 0.0,
+            beams: Vec4::ZERO,
+            red: palette.beam(BeamColor::Red),
+            green: palette.beam(BeamColor::Green),
+            blue: palette.beam(BeamColor::Blue),
+            symbol,
+        }
+    }
+}
+
 pub fn update_enemy_material(
-    mut parents: Query<(&mut TextureAtlasSprite, &Killable)>,
+    mut parents: Query<(
+        &mut TextureAtlasSprite,
+        Option<&Killable>,
+        &Colour,
+        Option<&Dying>,
+    )>,
     children: Query<(&Handle<EnemyMaterial>, &Parent)>,
     mut a: ResMut<Assets<EnemyMaterial>>,
     time: Res<Time>,
+    palette: Res<Palette>,
+    pulse: Res<MusicPulse>,
 ) {
     for (handle, parent) in &children {
         if let Some(mat) = a.get_mut(handle) {
             mat.time = time.seconds_since_startup() as f32;
-            if let Ok((_, killable)) = parents.get(**parent) {
-                mat.damaged = killable.under_damage.then_some(1.0).unwrap_or_default();
+            mat.pulse = pulse.0;
+            if let Ok((_, killable, colour, dying)) = parents.get(**parent) {
+                mat.color = palette.colour(*colour);
+                mat.red = palette.beam(BeamColor::Red);
+                mat.green = palette.beam(BeamColor::Green);
+                mat.blue = palette.beam(BeamColor::Blue);
+                mat.dissolve = dying.map_or(0.0, |d| d.0.percent());
+                match killable {
+                    Some(killable) => {
+                        mat.damaged = if killable.under_damage { 1.0 } else { 0.0 };
+                        mat.progress = killable.progress;
+                        let required = colour.made_by();
+                        let state = |beam| {
+                            if killable.hitting.contains(&beam) {
+                                1.0
+                            } else if required.contains(&beam) {
+                                0.5
+                            } else {
+                                0.0
+                            }
+                        };
+                        mat.beams = Vec4::new(
+                            state(BeamColor::Red),
+                            state(BeamColor::Green),
+                            state(BeamColor::Blue),
+                            0.0,
+                        );
+                    }
+                    // no point showing what's missing anymore
+                    None => {
+                        mat.damaged = 0.0;
+                        mat.beams = Vec4::ZERO;
+                    }
+                }
             }
         }
     }
 
-    for (mut atlas, killable) in &mut parents {
+    for (mut atlas, killable, _, _) in &mut parents {
+        // dying enemies are faded by `dissolve_dying_enemies`
+        let killable = match killable {
+            Some(k) => k,
+            None => continue,
+        };
         let s = (time.seconds_since_startup() * 6.0).sin() * 0.2 + 0.8;
-        let a = killable.under_damage.then_some(s).unwrap_or(1.0);
+        let a = if killable.under_damage { s } else { 1.0 };
         atlas.color.set_a(a as f32);
     }
 }
 
+/// the frames of symbols.aseprite
 #[derive(AssetCollection)]
 pub struct EnemySymbols {
-    #[asset(path = "sprites/symbols/red.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame0")]
     red: Handle<Image>,
-    #[asset(path = "sprites/symbols/green.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame1")]
     green: Handle<Image>,
-    #[asset(path = "sprites/symbols/blue.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame2")]
     blue: Handle<Image>,
-    #[asset(path = "sprites/symbols/yellow.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame3")]
     yellow: Handle<Image>,
-    #[asset(path = "sprites/symbols/magenta.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame5")]
     magenta: Handle<Image>,
-    #[asset(path = "sprites/symbols/cyan.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame4")]
     cyan: Handle<Image>,
-    #[asset(path = "sprites/symbols/white.png")]
+    #[asset(path = "sprites/symbols.aseprite#frame6")]
     white: Handle<Image>,
 }
 
+impl EnemySymbols {
+    pub fn get(&self, colour: Colour) -> Handle<Image> {
+        match colour {
+            Colour::Red => self.red.clone(),
+            Colour::Green => self.green.clone(),
+            Colour::Blue => self.blue.clone(),
+            Colour::Yellow => self.yellow.clone(),
+            Colour::Magenta => self.magenta.clone(),
+            Colour::Cyan => self.cyan.clone(),
+            Colour::White => self.white.clone(),
+        }
+    }
+}
+
 pub struct EnemyAssets {
     pub enemy: Handle<TextureAtlas>,
 }
 
 impl FromWorld for EnemyAssets {
     fn from_world(world: &mut World) -> Self {
-        let cell = world.cell();
-        let mut textures = cell.get_resource_mut::<Assets<TextureAtlas>>().unwrap();
-
-        let game_assets = cell
-            .get_resource::<GameAssets>()
-            .expect("Failed to get GameAssets");
-
-        let enemy = textures.add(TextureAtlas::from_grid(
-            game_assets.enemy.clone(),
-            Vec2::new(48.0, 48.0),
-            2,
-            1,
-        ));
+        let game_assets = world.resource::<GameAssets>();
+        let enemy = world
+            .resource::<Assets<Aseprite>>()
+            .get(&game_assets.enemy)
+            .expect("Failed to get enemy.aseprite")
+            .atlas
+            .clone();
         Self { enemy }
     }
 }
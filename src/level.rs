@@ -0,0 +1,248 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct LevelBeam {
+    pub color: BeamColor,
+    pub pivot: Vec2,
+}
+
+/// a block that stops beams from going through it
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Obstacle {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Obstacle {
+    pub fn contains(&self, point: Vec2) -> bool {
+        let d = (point - self.position).abs();
+        d.x <= self.size.x / 2.0 && d.y <= self.size.y / 2.0
+    }
+
+    /// whether the segment between `from` and `to` goes through the obstacle
+    pub fn blocks(&self, from: Vec2, to: Vec2) -> bool {
+        let min = self.position - self.size / 2.0;
+        let max = self.position + self.size / 2.0;
+        let dir = to - from;
+
+        // slab test, clipping the segment against each axis in turn
+        let mut t0 = 0.0f32;
+        let mut t1 = 1.0f32;
+        for (origin, d, lo, hi) in [(from.x, dir.x, min.x, max.x), (from.y, dir.y, min.y, max.y)] {
+            if d.abs() < f32::EPSILON {
+                if origin < lo || origin > hi {
+                    return false;
+                }
+                continue;
+            }
+            let (a, b) = ((lo - origin) / d, (hi - origin) / d);
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+            if t0 > t1 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ScriptedSpawn {
+    /// seconds since the start of the run
+    pub time: f64,
+    /// index into `Level::lanes`
+    pub lane: usize,
+    pub colour: Colour,
+}
+
+/// the layout of the arena, loaded from a `.level` file written in ron
+#[derive(Serialize, Deserialize, Clone, TypeUuid)]
+#[uuid = "6f3b2d1e-8a4c-4b7e-9d2f-0c1a5e3b7d9f"]
+#[serde(default)]
+pub struct Level {
+    pub beams: Vec<LevelBeam>,
+    /// heights where enemies come in
+    pub lanes: Vec<f32>,
+    pub player_min: Vec2,
+    pub player_max: Vec2,
+    pub obstacles: Vec<Obstacle>,
+    /// when not empty, these replace the random spawns until they run out
+    pub spawns: Vec<ScriptedSpawn>,
+    /// seconds of kill progress enemies get back every second none of their beams hit them,
+    /// at 0 they never heal
+    pub kill_decay: f32,
+    /// multiplies how long enemies of a colour take to kill, colours that aren't here use 1
+    pub kill_time_scale: Vec<(Colour, f32)>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            beams: vec![
+                LevelBeam {
+                    color: BeamColor::Red,
+                    pivot: Vec2::new(PIVOT_X, LANES[0]),
+                },
+                LevelBeam {
+                    color: BeamColor::Green,
+                    pivot: Vec2::new(PIVOT_X, LANES[1]),
+                },
+                LevelBeam {
+                    color: BeamColor::Blue,
+                    pivot: Vec2::new(PIVOT_X, LANES[2]),
+                },
+            ],
+            lanes: LANES.to_vec(),
+            player_min: PLAYER_MIN,
+            player_max: PLAYER_MAX,
+            obstacles: vec![],
+            spawns: vec![],
+            kill_decay: 0.0,
+            kill_time_scale: vec![],
+        }
+    }
+}
+
+impl Level {
+    /// enemies walk straight until they pass the pivots, and then go for the player
+    pub fn home_x(&self) -> f32 {
+        self.beams
+            .iter()
+            .map(|b| b.pivot.x)
+            .fold(f32::MIN, f32::max)
+    }
+
+    pub fn last_spawn_time(&self) -> f64 {
+        self.spawns.iter().map(|s| s.time).fold(0.0, f64::max)
+    }
+
+    /// lane height for an index, clamped so broken level files don't crash
+    pub fn lane(&self, index: usize) -> f32 {
+        self.lanes
+            .get(index.min(self.lanes.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn kill_time_scale(&self, colour: Colour) -> f32 {
+        self.kill_time_scale
+            .iter()
+            .find(|(c, _)| *c == colour)
+            .map_or(1.0, |(_, scale)| *scale)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, default())
+    }
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level: Level = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level"]
+    }
+}
+
+#[derive(AssetCollection)]
+pub struct LevelAssets {
+    /// the layout for normal runs
+    #[asset(path = "levels/arena.level")]
+    pub arena: Handle<Level>,
+    #[asset(path = "levels/main.campaign")]
+    pub campaign: Handle<Campaign>,
+}
+
+impl LevelAssets {
+    pub fn arena(&self, levels: &Assets<Level>) -> Level {
+        levels.get(&self.arena).cloned().unwrap_or_default()
+    }
+
+    pub fn campaign<'a>(&self, campaigns: &'a Assets<Campaign>) -> Option<&'a Campaign> {
+        campaigns.get(&self.campaign)
+    }
+}
+
+/// the level that `game_setup` builds the arena from
+#[derive(Default, Deref, DerefMut)]
+pub struct CurrentLevel(pub Level);
+
+#[derive(Component)]
+pub struct ObstacleMarker;
+
+pub fn obstacle_sprite(obstacle: &Obstacle) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgb(0.3, 0.3, 0.35),
+            custom_size: Some(obstacle.size),
+            ..default()
+        },
+        // above the beams, so they look like they go under it
+        transform: Transform::from_translation(obstacle.position.extend(3.0)),
+        ..default()
+    }
+}
+
+pub fn spawn_obstacles(commands: &mut Commands, level: &Level) {
+    for obstacle in &level.obstacles {
+        commands
+            .spawn_bundle(obstacle_sprite(obstacle))
+            .insert(ObstacleMarker)
+            .insert(CleanupGame);
+    }
+}
+
+/// queues the level's scripted spawns, like `plan_rhythm_spawns` does for beat maps
+pub fn plan_scripted_spawns(
+    mode: Res<GameMode>,
+    level: Res<CurrentLevel>,
+    mut queue: ResMut<SpawnQueue>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+    mut planned_until: Local<f64>,
+    mut spawner: WarningSpawner,
+) {
+    if *mode == GameMode::Rhythm || level.spawns.is_empty() {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    if *planned_until < game_start.0 {
+        *planned_until = game_start.0 - 1.0;
+    }
+    let until = now + SPAWN_WARNING_SECONDS;
+
+    let mut spawns = level
+        .spawns
+        .iter()
+        .map(|s| (game_start.0 + s.time, s))
+        .filter(|(at, _)| *at > *planned_until && *at <= until)
+        .collect::<Vec<_>>();
+    // the queue has to stay in order
+    spawns.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    for (at, spawn) in spawns {
+        let planned = spawner.plan(level.lane(spawn.lane), spawn.colour, at.max(now));
+        queue.push_back(planned);
+    }
+
+    *planned_until = until;
+}
@@ -0,0 +1,155 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+    Russian,
+}
+
+impl Language {
+    pub fn next(&self) -> Self {
+        match self {
+            Language::English => Language::Spanish,
+            Language::Spanish => Language::Russian,
+            Language::Russian => Language::English,
+        }
+    }
+}
+
+/// a table of translated strings, loaded from a `.lang` file
+///
+/// each line is `key = value`, lines starting with `#` are comments.
+/// `{}` in a value gets replaced by the arguments, in order
+#[derive(TypeUuid, Default)]
+#[uuid = "3b6c1a2e-5f0d-4c4e-9a77-0c8f2d1e6b51"]
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Locale {
+    fn parse(s: &str) -> Self {
+        let strings = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        Self { strings }
+    }
+
+    /// returns the key itself if it's missing, so untranslated strings are easy to spot
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// arguments that are keys themselves get translated too
+    pub fn format(&self, key: &str, args: &[String]) -> String {
+        let mut s = self.get(key).to_string();
+        for arg in args {
+            s = s.replacen("{}", self.get(arg), 1);
+        }
+        s
+    }
+}
+
+#[derive(Default)]
+pub struct LocaleLoader;
+
+impl AssetLoader for LocaleLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let locale = Locale::parse(std::str::from_utf8(bytes)?);
+            load_context.set_default_asset(LoadedAsset::new(locale));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lang"]
+    }
+}
+
+#[derive(AssetCollection)]
+pub struct LocaleAssets {
+    #[asset(path = "locales/en.lang")]
+    en: Handle<Locale>,
+    #[asset(path = "locales/es.lang")]
+    es: Handle<Locale>,
+    #[asset(path = "locales/ru.lang")]
+    ru: Handle<Locale>,
+}
+
+impl LocaleAssets {
+    pub fn get<'a>(&self, locales: &'a Assets<Locale>, language: Language) -> &'a Locale {
+        let handle = match language {
+            Language::English => &self.en,
+            Language::Spanish => &self.es,
+            Language::Russian => &self.ru,
+        };
+        locales.get(handle).expect("locale should be loaded")
+    }
+}
+
+/// picks `gameplay.ttf` when it can draw the whole string, and the fallback font otherwise
+pub fn font_for(text: &str, a: &GameAssets) -> Handle<Font> {
+    // gameplay.ttf only covers printable ascii
+    if text.is_ascii() {
+        a.font.clone()
+    } else {
+        a.fallback_font.clone()
+    }
+}
+
+/// a text whose first section is filled in from the current `Locale`
+#[derive(Component)]
+pub struct Localized {
+    pub key: &'static str,
+    pub args: Vec<String>,
+}
+
+impl Localized {
+    pub fn new(key: &'static str) -> Self {
+        Self { key, args: vec![] }
+    }
+
+    pub fn with_args(key: &'static str, args: Vec<String>) -> Self {
+        Self { key, args }
+    }
+}
+
+pub fn update_localized_text(
+    mut texts: Query<(&mut Text, &Localized, ChangeTrackers<Localized>)>,
+    language: Res<Language>,
+    locales: Res<Assets<Locale>>,
+    // these only exist once loading is done
+    la: Option<Res<LocaleAssets>>,
+    a: Option<Res<GameAssets>>,
+) {
+    let (la, a) = match (la, a) {
+        (Some(la), Some(a)) => (la, a),
+        _ => return,
+    };
+
+    let locale = la.get(&locales, *language);
+    for (mut text, localized, tracker) in &mut texts {
+        if !language.is_changed() && !tracker.is_changed() {
+            continue;
+        }
+
+        let value = locale.format(localized.key, &localized.args);
+        text.sections[0].style.font = font_for(&value, &a);
+        text.sections[0].value = value;
+    }
+}
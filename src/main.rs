@@ -1,28 +1,69 @@
 #![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
 use bevy::{
+    audio::{play_queued_audio_system, AudioOutput},
+    ecs::system::EntityCommands,
     math::Vec3Swizzles,
     prelude::*,
     sprite::{Material2dPlugin, MaterialMesh2dBundle},
 };
 use bevy_asset_loader::prelude::*;
+use serde::{Deserialize, Serialize};
 
+mod animation;
+mod arena;
+mod aseprite;
 mod audio;
 mod beams;
+mod campaign;
+mod coop;
+mod daily;
 mod death_screen;
+mod editor;
 mod enemies;
+mod level;
+mod locale;
 mod menu;
+mod modifiers;
 mod mouse;
+mod music;
+mod mutators;
+mod net;
+mod palette;
 mod player;
+mod rhythm;
+mod save;
+mod shield;
+mod shop;
+mod synth;
 mod ui;
 
+use animation::*;
+use arena::*;
+use aseprite::*;
 use audio::*;
 use beams::*;
+use campaign::*;
+use coop::*;
+use daily::*;
 use death_screen::*;
+use editor::*;
 use enemies::*;
+use level::*;
+use locale::*;
 use menu::*;
+use modifiers::*;
 use mouse::*;
+use music::*;
+use mutators::*;
+use net::*;
+use palette::*;
 use player::*;
+use rhythm::*;
+use save::*;
+use shield::*;
+use shop::*;
+use synth::*;
 use ui::*;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -31,19 +72,58 @@ pub enum GameState {
     Menu,
     Game,
     Death,
+    Editor,
+    LevelSelect,
+    Mutators,
+    /// buying upgrades between runs
+    Shop,
+    /// playing along with a host over the network
+    Remote,
 }
 
 pub const BEAM_LENGTH: f32 = 1000.0;
 pub const ENEMY_RADIUS: f32 = 50.0;
+pub const BEAM_WIDTH: f32 = 40.0;
 pub const NORMAL_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 pub const HOVERED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.3);
 pub const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
 
 fn main() {
+    let net = NetSession::from_args();
+    let coop = CoopSettings {
+        enabled: net.role == NetRole::Host,
+        ..default()
+    };
+
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
+        .insert_resource(WindowDescriptor {
+            title: "luminity".to_string(),
+            width: ARENA_WIDTH,
+            height: ARENA_HEIGHT,
+            fit_canvas_to_parent: true,
+            ..default()
+        })
         .add_plugins(DefaultPlugins)
         .add_state(GameState::Loading)
+        .add_asset::<Locale>()
+        .add_asset::<Melodies>()
+        .add_asset::<BeatMap>()
+        .init_asset_loader::<BeatMapLoader>()
+        .add_asset::<Level>()
+        .init_asset_loader::<LevelLoader>()
+        .add_asset::<Aseprite>()
+        .init_asset_loader::<AsepriteLoader>()
+        .add_asset::<AnimationSet>()
+        .init_asset_loader::<AnimationSetLoader>()
+        .add_asset::<Campaign>()
+        .init_asset_loader::<CampaignLoader>()
+        .add_asset::<Synth>()
+        .init_non_send_resource::<AudioOutput<Synth>>()
+        .init_resource::<Audio<Synth>>()
+        .add_system_to_stage(CoreStage::PostUpdate, play_queued_audio_system::<Synth>)
+        .init_asset_loader::<MelodiesLoader>()
+        .init_asset_loader::<LocaleLoader>()
         .add_plugin(Material2dPlugin::<BeamMaterial>::default())
         .add_plugin(Material2dPlugin::<EnemyMaterial>::default())
         // .add_plugin(bevy_inspector_egui::WorldInspectorPlugin::new())
@@ -53,65 +133,194 @@ fn main() {
                 .with_collection::<AudioAssets>()
                 .with_collection::<GameAssets>()
                 .with_collection::<EnemySymbols>()
+                .with_collection::<LocaleAssets>()
+                .with_collection::<LevelAssets>()
                 .init_resource::<PlayerAssets>()
-                .init_resource::<EnemyAssets>(),
+                .init_resource::<EnemyAssets>()
+                .with_collection::<AnimationAssets>()
+                .init_resource::<SpriteSheets>(),
         )
         .init_resource::<MousePos>()
+        .init_resource::<Palette>()
+        .init_resource::<Language>()
+        .init_resource::<MusicDirector>()
+        .init_resource::<MusicClock>()
+        .init_resource::<MusicPulse>()
+        .init_resource::<ScheduledNotes>()
+        .init_resource::<BeamHum>()
+        .init_resource::<GameMode>()
+        .init_resource::<RhythmScore>()
+        .insert_resource(SaveData::load())
+        .init_resource::<CurrentLevel>()
+        .init_resource::<EditorState>()
+        .init_resource::<TestPlay>()
+        .init_resource::<CampaignRun>()
+        .init_resource::<RunModifiers>()
+        .init_resource::<GameRng>()
+        .init_resource::<SelectedMutators>()
+        .init_resource::<DailyDay>()
         .insert_resource(EnemiesKilled(0))
         .insert_resource(GameStartTime(0.0))
-        .insert_resource(ClosestBeam(BeamColor::Green))
+        .insert_resource(coop)
+        .init_resource::<MovementConfig>()
+        .insert_resource(net)
+        .init_resource::<RemoteInput>()
+        .init_resource::<LatestSnapshot>()
+        .init_resource::<RemoteEnemies>()
         .insert_resource(EnemySpawnerTimer(Timer::from_seconds(1.0, true)))
-        .insert_resource(PlayerHealth { health: 30 })
+        .init_resource::<SpawnQueue>()
+        .insert_resource(PlayerHealth {
+            health: STARTING_HEALTH,
+            max: STARTING_HEALTH,
+        })
         .add_event::<EnemyDead>()
+        .add_event::<PlayerHit>()
         .add_system(button_interaction)
         .add_system(update_mouse_pos)
-        .add_system(animate_sprite)
-        .add_system_set(SystemSet::on_exit(GameState::Loading).with_system(setup))
+        .add_system(animate_sprites)
+        .add_system(update_localized_text)
+        .add_system(mix_music)
+        .add_system(update_music_pulse)
+        .add_system(host_network)
+        .add_system(client_network)
+        .add_system_set(
+            SystemSet::on_exit(GameState::Loading)
+                .with_system(setup)
+                .with_system(start_music),
+        )
         .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(setup_menu))
         .add_system_set(
             SystemSet::on_update(GameState::Menu)
                 .with_system(menu)
+                .with_system(palette_button)
+                .with_system(language_button)
+                .with_system(coop_buttons)
+                .with_system(update_menu_light_colors)
                 .with_system(rotate_menu_lights)
                 .with_system(menu_title_parallax),
         )
         .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(cleanup::<CleanupMenu>))
         .add_system_set(
             SystemSet::on_enter(GameState::Game)
-                .with_system(game_setup)
-                .with_system(setup_ui),
+                .with_system(game_setup.after(prepare_run))
+                .with_system(setup_ui.after(game_setup))
+                .with_system(setup_campaign_run)
+                .with_system(prepare_run)
+                .with_system(setup_modifiers_ui.after(prepare_run))
+                .with_system(start_beam_hum)
+                .with_system(announce_run.after(prepare_run)),
         )
         .add_system_set(
             SystemSet::on_update(GameState::Game)
-                .with_system(move_player)
+                .with_system(move_player.after(host_network))
+                .with_system(blink_invulnerable_players)
+                .with_system(use_shield.before(damage_player).after(host_network))
+                .with_system(add_shield_bubbles)
+                .with_system(show_shield_bubbles)
+                .with_system(update_shield_ui)
                 .with_system(end_game_if_health_is_0)
-                .with_system(change_player_sprite)
-                .with_system(move_light_beam)
+                .with_system(update_player_animation)
+                .with_system(update_enemy_animation)
+                .with_system(update_aim)
+                .with_system(move_light_beam.after(update_aim))
                 .with_system(update_beam_material)
                 .with_system(update_closest_beam)
+                .with_system(plan_enemy_spawns)
+                .with_system(plan_rhythm_spawns)
+                .with_system(plan_scripted_spawns)
+                .with_system(leave_test_play.after(end_game_if_health_is_0))
+                .with_system(count_campaign_kills)
+                .with_system(damage_campaign_target)
+                .with_system(check_objective.after(end_game_if_health_is_0))
+                .with_system(update_objective_ui)
+                .with_system(judge_rhythm_kills)
+                .with_system(animate_judgement_popups)
+                .with_system(update_spawn_warnings)
                 .with_system(spawn_enemies)
                 .with_system(move_enemies)
                 .with_system(damage_enemies)
-                .with_system(play_audio_when_enemy_dies)
+                .with_system(dissolve_dying_enemies)
+                .with_system(schedule_notes_when_enemy_dies)
+                .with_system(play_scheduled_notes)
+                .with_system(play_hit_sound)
+                .with_system(update_beam_hum)
                 .with_system(damage_player)
                 .with_system(update_enemy_material)
                 .with_system(update_player_health_ui)
-                .with_system(update_points_ui),
+                .with_system(update_points_ui)
+                .with_system(update_music_intensity)
+                .with_system(update_time_ui)
+                .with_system(update_selected_beam_ui)
+                .with_system(send_snapshot),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Game)
+                .with_system(cleanup::<CleanupGame>)
+                .with_system(stop_beam_hum)
+                .with_system(clear_scheduled_notes)
+                .with_system(record_rhythm_score)
+                .with_system(record_daily_score)
+                .with_system(earn_shards)
+                .with_system(announce_end),
         )
-        .add_system_set(SystemSet::on_exit(GameState::Game).with_system(cleanup::<CleanupGame>))
         .add_system_set(SystemSet::on_enter(GameState::Death).with_system(setup_death_screen))
         .add_system_set(SystemSet::on_update(GameState::Death).with_system(death_screen))
         .add_system_set(SystemSet::on_exit(GameState::Death).with_system(cleanup::<CleanupDeath>))
+        .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(setup_editor))
+        .add_system_set(
+            SystemSet::on_update(GameState::Editor)
+                .with_system(editor_input)
+                .with_system(editor_buttons)
+                .with_system(finish_loading_level)
+                .with_system(redraw_editor),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Editor).with_system(cleanup::<CleanupEditor>))
+        .add_system_set(SystemSet::on_enter(GameState::LevelSelect).with_system(setup_level_select))
+        .add_system_set(SystemSet::on_update(GameState::LevelSelect).with_system(level_select))
+        .add_system_set(
+            SystemSet::on_exit(GameState::LevelSelect).with_system(cleanup::<CleanupLevelSelect>),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::Mutators).with_system(setup_mutators))
+        .add_system_set(SystemSet::on_update(GameState::Mutators).with_system(mutators_screen))
+        .add_system_set(
+            SystemSet::on_exit(GameState::Mutators).with_system(cleanup::<CleanupMutators>),
+        )
+        .add_system_set(SystemSet::on_enter(GameState::Shop).with_system(setup_shop))
+        .add_system_set(SystemSet::on_update(GameState::Shop).with_system(shop_screen))
+        .add_system_set(SystemSet::on_exit(GameState::Shop).with_system(cleanup::<CleanupShop>))
+        .add_system_set(
+            SystemSet::on_enter(GameState::Remote)
+                .with_system(game_setup)
+                .with_system(setup_ui.after(game_setup))
+                .with_system(clear_remote_enemies),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Remote)
+                .with_system(send_remote_input)
+                .with_system(apply_snapshot)
+                .with_system(add_shield_bubbles)
+                .with_system(show_shield_bubbles)
+                .with_system(update_shield_ui)
+                .with_system(update_player_animation)
+                .with_system(update_enemy_animation)
+                .with_system(dissolve_dying_enemies)
+                .with_system(update_beam_material)
+                .with_system(update_enemy_material)
+                .with_system(update_player_health_ui)
+                .with_system(update_points_ui)
+                .with_system(update_music_intensity)
+                .with_system(update_time_ui)
+                .with_system(update_selected_beam_ui),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Remote).with_system(cleanup::<CleanupGame>))
         .run();
 }
 
-fn setup(mut commands: Commands, a: Res<AssetServer>, aa: Res<AudioAssets>, audio: Res<Audio>) {
+fn setup(mut commands: Commands, a: Res<AssetServer>) {
     a.watch_for_changes().unwrap();
 
-    commands
-        .spawn_bundle(Camera2dBundle::default())
-        .insert(MainCamera);
-
-    audio.play_with_settings(aa.music.clone(), PlaybackSettings::LOOP);
+    spawn_camera(&mut commands);
+    spawn_letterbox(&mut commands);
 }
 
 pub struct GameStartTime(f64);
@@ -123,37 +332,60 @@ fn game_setup(
 
     mut health: ResMut<PlayerHealth>,
     mut score: ResMut<EnemiesKilled>,
+    mut rhythm: ResMut<RhythmScore>,
     mut start: ResMut<GameStartTime>,
+    mut queue: ResMut<SpawnQueue>,
     time: Res<Time>,
-    a: Res<PlayerAssets>,
+    (a, animations): (Res<PlayerAssets>, Res<AnimationAssets>),
+    symbols: Res<EnemySymbols>,
+    palette: Res<Palette>,
+    level: Res<CurrentLevel>,
+    modifiers: Res<RunModifiers>,
+    coop: Res<CoopSettings>,
+    net: Res<NetSession>,
 ) {
     // reset resources
-    health.health = 30;
+    health.max = coop.starting_health(modifiers.starting_health);
+    health.health = health.max;
     score.0 = 0;
+    rhythm.score = 0;
     start.0 = time.seconds_since_startup();
+    queue.clear();
 
     let mesh = meshes.add(Mesh::from(shape::Quad::default()));
 
-    let beams = [
-        (BeamColor::Red, Vec2::new(-500.0, 120.0), 0.0),
-        (BeamColor::Green, Vec2::new(-500.0, 0.0), 12.0),
-        (BeamColor::Blue, Vec2::new(-500.0, -120.0), 30.0),
-    ];
-    for (color, pivot, offset) in beams {
-        let mut c = color.color();
+    for &LevelBeam { color, pivot } in &level.beams {
+        // so the beams don't wiggle in sync
+        let offset = match color {
+            BeamColor::Red => 0.0,
+            BeamColor::Green => 12.0,
+            BeamColor::Blue => 30.0,
+        };
+        let mut c = palette.beam(color);
         c.set_a(0.5);
         let material = beam_mats.add(BeamMaterial {
             color: c,
             time: 0.0,
             offset,
             selected: 0.0,
+            glyphs: 0.0,
+            pulse: 0.0,
+            symbol: symbols.get(color.colour()),
         });
         commands
             .spawn_bundle(MaterialMesh2dBundle {
                 mesh: mesh.clone().into(),
                 transform: Transform::default()
-                    .with_translation(Vec3::new(pivot.x + BEAM_LENGTH / 2.0, pivot.y, 0.0))
-                    .with_scale(Vec3::new(BEAM_LENGTH, 40.0, 1.0)),
+                    .with_translation(Vec3::new(
+                        pivot.x + modifiers.scaled_beam_length() / 2.0,
+                        pivot.y,
+                        0.0,
+                    ))
+                    .with_scale(Vec3::new(
+                        modifiers.scaled_beam_length(),
+                        BEAM_WIDTH * modifiers.beam_width,
+                        1.0,
+                    )),
                 material,
                 ..default()
             })
@@ -162,19 +394,38 @@ fn game_setup(
             .insert(CleanupGame);
     }
 
-    // player
-    commands
-        .spawn_bundle(SpriteSheetBundle {
-            transform: Transform::default().with_translation(Vec3::new(-550.0, 0.0, 1.0)),
+    spawn_obstacles(&mut commands, &level);
+
+    // players
+    let centre = (level.player_min + level.player_max) / 2.0;
+    let players = coop.players();
+    for (index, tint) in PLAYER_TINTS.into_iter().take(players).enumerate() {
+        // spread out vertically
+        let offset = (index as f32 - (players - 1) as f32 / 2.0) * 60.0;
+        let start = centre + Vec2::new(0.0, offset);
+        let mut player = commands.spawn_bundle(SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: tint,
+                ..default()
+            },
+            transform: Transform::default().with_translation(start.extend(1.0)),
             texture_atlas: a.player.clone(),
             ..default()
-        })
-        .insert(CleanupGame)
-        .insert(Player)
-        .insert(AnimationTimer {
-            timer: Timer::from_seconds(0.1, true),
-            paused: false,
         });
+        player
+            .insert(CleanupGame)
+            .insert(Player { index })
+            .insert(Controls::for_player(index, net.role == NetRole::Host))
+            .insert(Aim(Vec2::new(ARENA_WIDTH / 2.0, start.y)))
+            .insert(ClosestBeam(BeamColor::Green))
+            .insert(Velocity::default())
+            .insert(Dash::default())
+            .insert(Shield::default())
+            .insert(Animator::new(animations.player.clone(), "idle"));
+        if !coop.shared_health {
+            player.insert(Health(modifiers.starting_health));
+        }
+    }
 }
 
 //
@@ -185,6 +436,14 @@ pub struct CleanupMenu;
 pub struct CleanupGame;
 #[derive(Component)]
 pub struct CleanupDeath;
+#[derive(Component)]
+pub struct CleanupEditor;
+#[derive(Component)]
+pub struct CleanupLevelSelect;
+#[derive(Component)]
+pub struct CleanupMutators;
+#[derive(Component)]
+pub struct CleanupShop;
 pub fn cleanup<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
     for entity in &query {
         commands.entity(entity).despawn_recursive();
@@ -196,11 +455,14 @@ pub fn cleanup<T: Component>(mut commands: Commands, query: Query<Entity, With<T
 #[derive(Component)]
 pub struct MainCamera;
 #[derive(Component)]
-pub struct Player;
+pub struct Player {
+    /// 0 for the first player, 1 for the second one in co-op
+    pub index: usize,
+}
 #[derive(Component)]
 pub struct Pivot(Vec2);
 
-#[derive(Component, PartialEq, Eq, Copy, Clone)]
+#[derive(Component, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub enum BeamColor {
     Red,
     Green,
@@ -208,11 +470,11 @@ pub enum BeamColor {
 }
 
 impl BeamColor {
-    fn color(&self) -> Color {
+    fn colour(&self) -> Colour {
         match self {
-            BeamColor::Red => Color::rgb(1.0, 0.0, 0.0),
-            BeamColor::Green => Color::rgb(0.0, 1.0, 0.0),
-            BeamColor::Blue => Color::rgb(0.0, 0.0, 1.0),
+            BeamColor::Red => Colour::Red,
+            BeamColor::Green => Colour::Green,
+            BeamColor::Blue => Colour::Blue,
         }
     }
 }
@@ -222,9 +484,13 @@ pub struct Enemy;
 pub struct Killable {
     seconds: f32,
     under_damage: bool,
+    /// how far along the kill is, from 0 to 1
+    progress: f32,
+    /// the beams it needs that are hitting it right now
+    hitting: Vec<BeamColor>,
 }
 
-#[derive(Component, PartialEq, Eq, Copy, Clone)]
+#[derive(Component, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub enum Colour {
     Red,
     Green,
@@ -249,6 +515,24 @@ const ALL_COLORS: [Colour; 7] = [
 ];
 
 impl Colour {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_COLORS
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Yellow => "yellow",
+            Self::Magenta => "magenta",
+            Self::Cyan => "cyan",
+            Self::White => "white",
+        }
+    }
+
     fn made_by(&self) -> Vec<BeamColor> {
         use BeamColor::*;
         match self {
@@ -261,30 +545,23 @@ impl Colour {
             Self::White => vec![Red, Green, Blue],
         }
     }
-
-    fn color(&self) -> Color {
-        let mut c = self
-            .made_by()
-            .into_iter()
-            .map(|c| c.color())
-            .fold(Color::NONE, |a, b| a + b);
-        c.set_a(1.0);
-        c
-    }
 }
 
 #[derive(AssetCollection)]
 pub struct GameAssets {
     #[asset(path = "fonts/gameplay.ttf")]
     font: Handle<Font>,
-    #[asset(path = "sprites/player.png")]
-    player: Handle<Image>,
+    /// for scripts that gameplay.ttf doesn't cover
+    #[asset(path = "fonts/DejaVuSans.ttf")]
+    fallback_font: Handle<Font>,
+    #[asset(path = "sprites/player.aseprite")]
+    player: Handle<Aseprite>,
     #[asset(path = "sprites/player-neutral.png")]
     player_neutral: Handle<Image>,
     #[asset(path = "sprites/player-sad.png")]
     player_sad: Handle<Image>,
-    #[asset(path = "sprites/enemy.png")]
-    enemy: Handle<Image>,
+    #[asset(path = "sprites/enemy.aseprite")]
+    enemy: Handle<Aseprite>,
 }
 
 pub fn button_interaction(
@@ -308,29 +585,45 @@ pub fn button_interaction(
     }
 }
 
-#[derive(Component)]
-pub struct AnimationTimer {
-    timer: Timer,
-    paused: bool,
-}
-fn animate_sprite(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(
-        &mut AnimationTimer,
-        &mut TextureAtlasSprite,
-        &Handle<TextureAtlas>,
-    )>,
-) {
-    for (mut timer, mut sprite, texture_atlas_handle) in &mut query {
-        if timer.paused {
-            sprite.index = 0;
-        } else {
-            timer.timer.tick(time.delta());
-            if timer.timer.just_finished() {
-                let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-                sprite.index = (sprite.index + 1) % texture_atlas.textures.len();
-            }
-        }
+/// a horizontal container for buttons
+pub fn button_row() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
     }
 }
+
+pub fn spawn_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    a: &GameAssets,
+    label: Localized,
+    font_size: f32,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = parent.spawn_bundle(ButtonBundle {
+        style: Style {
+            padding: UiRect::all(Val::Px(10.0)),
+            margin: UiRect::all(Val::Px(5.0)),
+            ..default()
+        },
+        color: NORMAL_BUTTON.into(),
+        ..default()
+    });
+    button.with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: a.font.clone(),
+                    font_size,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ))
+            .insert(label);
+    });
+    button
+}
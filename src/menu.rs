@@ -5,6 +5,8 @@ pub fn setup_menu(
     a: Res<GameAssets>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    palette: Res<Palette>,
+    coop: Res<CoopSettings>,
 ) {
     commands
         .spawn_bundle(
@@ -87,26 +89,33 @@ pub fn setup_menu(
                 ..default()
             });
 
-            parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        padding: UiRect::all(Val::Px(10.0)),
-                        ..default()
-                    },
-                    color: NORMAL_BUTTON.into(),
-                    ..default()
-                })
-                .insert(PlayButton)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle::from_section(
-                        "Play",
-                        TextStyle {
-                            font: a.font.clone(),
-                            font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
-                        },
-                    ));
-                });
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                spawn_button(
+                    parent,
+                    &a,
+                    Localized::with_args("menu-colours", vec![palette.key().to_string()]),
+                    30.0,
+                )
+                .insert(PaletteButton);
+                spawn_button(parent, &a, Localized::new("menu-language"), 30.0)
+                    .insert(LanguageButton);
+            });
+
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                let (coop_label, shared_label) = coop_button_labels(&coop);
+                spawn_button(parent, &a, coop_label, 30.0).insert(CoopButton);
+                spawn_button(parent, &a, shared_label, 30.0).insert(SharedHealthButton);
+            });
+
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                spawn_button(parent, &a, Localized::new("menu-play"), 40.0).insert(PlayButton);
+                spawn_button(parent, &a, Localized::new("menu-rhythm"), 40.0).insert(RhythmButton);
+                spawn_button(parent, &a, Localized::new("menu-daily"), 40.0).insert(DailyButton);
+                spawn_button(parent, &a, Localized::new("menu-campaign"), 40.0)
+                    .insert(CampaignButton);
+                spawn_button(parent, &a, Localized::new("menu-editor"), 40.0)
+                    .insert(EditorMenuButton);
+            });
         });
 
     for (color, pos) in [
@@ -135,7 +144,7 @@ pub fn setup_menu(
     }
 
     let mesh = meshes.add(Mesh::from(shape::Circle::new(10.0)));
-    for (i, color) in [Color::RED, Color::GREEN, Color::BLUE]
+    for (i, color) in [BeamColor::Red, BeamColor::Green, BeamColor::Blue]
         .repeat(3)
         .into_iter()
         .enumerate()
@@ -144,10 +153,10 @@ pub fn setup_menu(
             .spawn_bundle(MaterialMesh2dBundle {
                 mesh: mesh.clone().into(),
                 transform: Transform::default(),
-                material: materials.add(ColorMaterial::from(color)),
+                material: materials.add(ColorMaterial::from(palette.beam(color))),
                 ..default()
             })
-            .insert(MenuFloatingLight(i + 1))
+            .insert(MenuFloatingLight(i + 1, color))
             .insert(CleanupMenu);
     }
 }
@@ -155,33 +164,146 @@ pub fn setup_menu(
 #[derive(Component)]
 pub struct PlayButton;
 
+#[derive(Component)]
+pub struct RhythmButton;
+
+#[derive(Component)]
+pub struct DailyButton;
+
+#[derive(Component)]
+pub struct CampaignButton;
+
+#[derive(Component)]
+pub struct EditorMenuButton;
+
 pub fn menu(
     mut state: ResMut<State<GameState>>,
-    mut interaction_query: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
+    mut mode: ResMut<GameMode>,
+    mut current: ResMut<CurrentLevel>,
+    mut test: ResMut<TestPlay>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<Level>>,
+    play: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
+    rhythm: Query<&Interaction, (Changed<Interaction>, With<RhythmButton>)>,
+    daily: Query<&Interaction, (Changed<Interaction>, With<DailyButton>)>,
+    campaign: Query<&Interaction, (Changed<Interaction>, With<CampaignButton>)>,
+    editor: Query<&Interaction, (Changed<Interaction>, With<EditorMenuButton>)>,
 ) {
-    for interaction in &mut interaction_query {
+    let mut start = |new_mode| {
+        *mode = new_mode;
+        current.0 = level_assets.arena(&levels);
+        test.0 = false;
+        // endless and rhythm runs can have mutators
+        let next = match new_mode {
+            GameMode::Endless | GameMode::Rhythm => GameState::Mutators,
+            _ => GameState::Game,
+        };
+        state.set(next).unwrap();
+    };
+    for interaction in &play {
+        if *interaction == Interaction::Clicked {
+            start(GameMode::Endless);
+        }
+    }
+    for interaction in &rhythm {
+        if *interaction == Interaction::Clicked {
+            start(GameMode::Rhythm);
+        }
+    }
+    for interaction in &daily {
+        if *interaction == Interaction::Clicked {
+            start(GameMode::Daily);
+        }
+    }
+    for interaction in &campaign {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::LevelSelect).unwrap();
+        }
+    }
+    for interaction in &editor {
         if *interaction == Interaction::Clicked {
-            state.set(GameState::Game).unwrap();
+            state.set(GameState::Editor).unwrap();
         }
     }
 }
 
 #[derive(Component)]
-pub struct MenuFloatingLight(usize);
+pub struct PaletteButton;
 
-pub fn rotate_menu_lights(mut query: Query<(&mut Transform, &MenuFloatingLight)>, time: Res<Time>) {
+pub fn palette_button(
+    mut palette: ResMut<Palette>,
+    interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<PaletteButton>),
+    >,
+    mut texts: Query<&mut Localized>,
+) {
+    for (interaction, children) in &interaction_query {
+        if *interaction == Interaction::Clicked {
+            *palette = palette.next();
+
+            for child in children {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.args = vec![palette.key().to_string()];
+                }
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LanguageButton;
+
+pub fn language_button(
+    mut language: ResMut<Language>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<LanguageButton>)>,
+) {
+    for interaction in &interaction_query {
+        if *interaction == Interaction::Clicked {
+            *language = language.next();
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MenuFloatingLight(usize, BeamColor);
+
+pub fn update_menu_light_colors(
+    query: Query<(&Handle<ColorMaterial>, &MenuFloatingLight)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    palette: Res<Palette>,
+) {
+    if !palette.is_changed() {
+        return;
+    }
+
+    for (handle, light) in &query {
+        if let Some(mat) = materials.get_mut(handle) {
+            mat.color = palette.beam(light.1);
+        }
+    }
+}
+
+pub fn rotate_menu_lights(
+    mut query: Query<(&mut Transform, &MenuFloatingLight)>,
+    time: Res<Time>,
+    pulse: Res<MusicPulse>,
+) {
     let t = time.seconds_since_startup();
+    // the lights jump out a bit and grow with the music
+    let spread = 200.0 * (1.0 + pulse.0 * 0.15);
     // make the friends go further when the button is pressed, but close in when activating a pillar
     for (mut trans, light) in query.iter_mut() {
         let i = light.0 as f64 / 2.0;
         let i2 = i / 2.0;
         let new_pos = Vec3::new(
-            200.0 * i2 as f32 * (t * 0.4 * i2 + i).cos() as f32,
-            200.0 * (t * 0.4 * i + i).sin() as f32,
+            spread * i2 as f32 * (t * 0.4 * i2 + i).cos() as f32,
+            spread * (t * 0.4 * i + i).sin() as f32,
             0.0,
         );
 
         trans.translation = new_pos;
+        trans.scale = Vec3::splat(1.0 + pulse.0 * 0.5);
     }
 }
 
@@ -0,0 +1,235 @@
+use crate::*;
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::Duration;
+
+/// tweaks to the rules that last for a whole run
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RunModifiers {
+    /// multiplies how fast enemies walk
+    pub enemy_speed: f32,
+    /// multiplies how often enemies spawn
+    pub spawn_rate: f32,
+    /// only spawn enemies that need more than one beam
+    pub only_mixed: bool,
+    /// a beam that stays where it is and can't be selected
+    pub locked_beam: Option<BeamColor>,
+    pub starting_health: i8,
+    /// multiplies how thick the beams are
+    pub beam_width: f32,
+    /// enemies come in on the opposite lane
+    pub mirror_lanes: bool,
+    pub score_multiplier: f32,
+    /// how long an enemy has to be lit to die, in seconds
+    pub kill_time: f32,
+    /// multiplies how long the beams are
+    pub beam_length: f32,
+    /// enemies also come in on a lane past the last one
+    pub extra_lane: bool,
+    /// seconds of kill progress an enemy gets back every second none of its beams hit it,
+    /// levels can ask for more
+    pub kill_decay: f32,
+    /// enemies slowly take damage from some of the beams they need, instead of only from all of them
+    pub partial_damage: bool,
+}
+
+impl Default for RunModifiers {
+    fn default() -> Self {
+        Self {
+            enemy_speed: 1.0,
+            spawn_rate: 1.0,
+            only_mixed: false,
+            locked_beam: None,
+            starting_health: STARTING_HEALTH,
+            beam_width: 1.0,
+            mirror_lanes: false,
+            score_multiplier: 1.0,
+            kill_time: 2.0,
+            beam_length: 1.0,
+            extra_lane: false,
+            kill_decay: 0.0,
+            partial_damage: false,
+        }
+    }
+}
+
+impl RunModifiers {
+    pub fn score(&self, kills: usize) -> usize {
+        (kills as f32 * self.score_multiplier).round() as usize
+    }
+
+    /// how far from the centre of a beam an enemy can be and still get hit
+    pub fn beam_reach(&self) -> f32 {
+        ENEMY_RADIUS + BEAM_WIDTH / 2.0 * (self.beam_width - 1.0)
+    }
+
+    pub fn scaled_beam_length(&self) -> f32 {
+        BEAM_LENGTH * self.beam_length
+    }
+
+    /// how long an enemy of this colour has to be lit to die, in seconds
+    pub fn kill_time(&self, colour: Colour, level: &Level) -> f32 {
+        self.kill_time * level.kill_time_scale(colour)
+    }
+
+    pub fn kill_decay(&self, level: &Level) -> f32 {
+        self.kill_decay.max(level.kill_decay)
+    }
+
+    /// the lanes random enemies can come in on
+    pub fn lanes(&self, level: &Level) -> Vec<f32> {
+        let mut lanes = level.lanes.clone();
+        if self.extra_lane && !lanes.is_empty() {
+            let min = lanes.iter().copied().fold(f32::MAX, f32::min);
+            let max = lanes.iter().copied().fold(f32::MIN, f32::max);
+            let spacing = if lanes.len() > 1 {
+                (max - min) / (lanes.len() - 1) as f32
+            } else {
+                120.0
+            };
+            lanes.push(max + spacing);
+        }
+        lanes
+    }
+
+    /// the lane an enemy actually comes in on
+    pub fn lane(&self, lane: f32, level: &Level) -> f32 {
+        if !self.mirror_lanes {
+            return lane;
+        }
+        let min = level.lanes.iter().copied().fold(f32::MAX, f32::min);
+        let max = level.lanes.iter().copied().fold(f32::MIN, f32::max);
+        min + max - lane
+    }
+
+    /// what the hud shows for the modifiers that are on
+    pub fn labels(&self) -> Vec<Localized> {
+        let mut labels = vec![];
+        if self.enemy_speed > 1.0 {
+            labels.push(Localized::new("modifier-fast-enemies"));
+        }
+        if self.spawn_rate > 1.0 {
+            labels.push(Localized::new("modifier-double-spawns"));
+        }
+        if self.only_mixed {
+            labels.push(Localized::new("modifier-only-mixed"));
+        }
+        if self.starting_health < STARTING_HEALTH {
+            labels.push(Localized::new("modifier-glass-cannon"));
+        }
+        if self.beam_width > 1.0 {
+            labels.push(Localized::new("modifier-wide-beams"));
+        }
+        if self.mirror_lanes {
+            labels.push(Localized::new("modifier-mirror-lanes"));
+        }
+        if self.extra_lane {
+            labels.push(Localized::new("modifier-extra-lane"));
+        }
+        if self.kill_decay > 0.0 {
+            labels.push(Localized::new("modifier-regenerating"));
+        }
+        if self.partial_damage {
+            labels.push(Localized::new("modifier-partial-damage"));
+        }
+        if let Some(beam) = self.locked_beam {
+            labels.push(Localized::with_args(
+                "modifier-locked-beam",
+                vec![format!("beam-{}", beam.colour().name())],
+            ));
+        }
+        if !labels.is_empty() && self.score_multiplier != 1.0 {
+            labels.push(Localized::with_args(
+                "modifier-score-multiplier",
+                vec![self.score_multiplier.to_string()],
+            ));
+        }
+        labels
+    }
+}
+
+/// randomness for the run, so that seeded runs play out the same
+#[derive(Deref, DerefMut)]
+pub struct GameRng(pub StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+pub fn prepare_run(
+    mode: Res<GameMode>,
+    selected: Res<SelectedMutators>,
+    save: Res<SaveData>,
+    mut modifiers: ResMut<RunModifiers>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<EnemySpawnerTimer>,
+    mut day: ResMut<DailyDay>,
+    test: Res<TestPlay>,
+) {
+    if *mode == GameMode::Daily {
+        day.0 = today();
+        *modifiers = daily_modifiers(day.0);
+        rng.0 = StdRng::seed_from_u64(day.0);
+    } else {
+        // the campaign and editor test play use the levels as they were made
+        *modifiers = match *mode {
+            GameMode::Endless | GameMode::Rhythm if !test.0 => {
+                let mut modifiers = RunModifiers::default();
+                save.upgrades.apply(&mut modifiers);
+                selected.apply(&mut modifiers);
+                modifiers
+            }
+            _ => default(),
+        };
+        rng.0 = StdRng::from_entropy();
+    }
+
+    timer
+        .0
+        .set_duration(Duration::from_secs_f32(1.0 / modifiers.spawn_rate));
+    timer.0.reset();
+}
+
+#[derive(Component)]
+pub struct ModifiersText;
+
+pub fn setup_modifiers_ui(mut cmd: Commands, a: Res<GameAssets>, modifiers: Res<RunModifiers>) {
+    let labels = modifiers.labels();
+    if labels.is_empty() {
+        return;
+    }
+
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            // under the colour legend
+            position: UiRect {
+                top: Val::Px(135.0),
+                right: Val::Px(15.0),
+                ..default()
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexEnd,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(CleanupGame)
+    .with_children(|parent| {
+        for label in labels {
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: a.font.clone(),
+                        font_size: 20.0,
+                        color: Color::rgb(1.0, 0.8, 0.4),
+                    },
+                ))
+                .insert(label)
+                .insert(ModifiersText);
+        }
+    });
+}
@@ -0,0 +1,247 @@
+use crate::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    audio::AudioSink,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+
+/// tempo of music.ogg, until its beat map says otherwise
+const MUSIC_BPM: f64 = 100.0;
+
+/// how fast the music volume moves towards its target, in volume per second
+const FADE_SPEED: f32 = 0.8;
+
+/// music volume in the menus, the game and on the death screen
+const MENU_VOLUME: f32 = 0.6;
+const GAME_VOLUME: f32 = 1.0;
+const DEATH_VOLUME: f32 = 0.2;
+/// how much of the volume in game depends on the intensity
+const INTENSITY_WEIGHT: f32 = 0.3;
+
+/// fades music.ogg depending on the game state and how intense the run is
+#[derive(Default)]
+pub struct MusicDirector {
+    sink: Handle<AudioSink>,
+    volume: f32,
+    /// from 0 to 1, how much danger the player is in
+    pub intensity: f32,
+}
+
+impl MusicDirector {
+    fn target(&self, state: &GameState) -> f32 {
+        match state {
+            GameState::Loading => 0.0,
+            GameState::Menu
+            | GameState::Editor
+            | GameState::LevelSelect
+            | GameState::Mutators
+            | GameState::Shop => MENU_VOLUME,
+            GameState::Game | GameState::Remote => {
+                GAME_VOLUME * (1.0 - INTENSITY_WEIGHT + INTENSITY_WEIGHT * self.intensity)
+            }
+            GameState::Death => DEATH_VOLUME,
+        }
+    }
+}
+
+/// keeps track of where the beats of the music are
+pub struct MusicClock {
+    /// time since startup when the music started playing
+    pub started_at: f64,
+    bpm: f64,
+}
+
+impl Default for MusicClock {
+    fn default() -> Self {
+        Self {
+            started_at: 0.0,
+            bpm: MUSIC_BPM,
+        }
+    }
+}
+
+impl MusicClock {
+    pub fn beat_length(&self) -> f64 {
+        60.0 / self.bpm
+    }
+
+    /// seconds into the current loop of a track `length` seconds long
+    pub fn position(&self, now: f64, length: f64) -> f64 {
+        (now - self.started_at).rem_euclid(length)
+    }
+
+    /// seconds to the closest beat, before or after `now`
+    pub fn distance_to_beat(&self, now: f64) -> f64 {
+        let beats = (now - self.started_at) / self.beat_length();
+        (beats - beats.round()).abs() * self.beat_length()
+    }
+
+    /// time of the next `1 / subdivisions` of a beat, at or after `now`
+    pub fn next_subdivision(&self, now: f64, subdivisions: f64) -> f64 {
+        let step = self.beat_length() / subdivisions;
+        self.started_at + ((now - self.started_at) / step).ceil() * step
+    }
+}
+
+pub fn start_music(
+    mut director: ResMut<MusicDirector>,
+    mut clock: ResMut<MusicClock>,
+    aa: Res<AudioAssets>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    maps: Res<Assets<BeatMap>>,
+    time: Res<Time>,
+) {
+    clock.started_at = time.seconds_since_startup();
+    if let Some(map) = maps.get(&aa.music_beats) {
+        clock.bpm = map.bpm;
+    }
+
+    // starts silent and fades in
+    let sink = audio.play_with_settings(aa.music.clone(), PlaybackSettings::LOOP.with_volume(0.0));
+    director.sink = sinks.get_handle(sink);
+    director.volume = 0.0;
+}
+
+pub fn update_music_intensity(
+    mut director: ResMut<MusicDirector>,
+    players: Query<&Transform, With<Player>>,
+    enemies: Query<&Transform, With<Enemy>>,
+    health: Res<PlayerHealth>,
+    time: Res<Time>,
+) {
+    let close = enemies
+        .iter()
+        .filter(|e| {
+            players
+                .iter()
+                .any(|p| e.translation.xy().distance(p.translation.xy()) < 400.0)
+        })
+        .count();
+
+    let crowd = (close as f32 / 5.0).min(1.0);
+    let hurt = 1.0 - (health.health as f32 / health.max as f32).clamp(0.0, 1.0);
+    let target = crowd.max(hurt);
+
+    // smooth it out so single enemies don't make the music jump around
+    let t = (time.delta_seconds() * 2.0).min(1.0);
+    director.intensity += (target - director.intensity) * t;
+}
+
+pub fn mix_music(
+    mut director: ResMut<MusicDirector>,
+    sinks: Res<Assets<AudioSink>>,
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+) {
+    let target = director.target(state.current());
+    let step = FADE_SPEED * time.delta_seconds();
+    director.volume += (target - director.volume).clamp(-step, step);
+
+    if let Some(sink) = sinks.get(&director.sink) {
+        sink.set_volume(director.volume);
+    }
+}
+
+/// timing information for a music track, loaded from a `.beats` file
+///
+/// each line is a command followed by its arguments, lines starting with `#` are comments:
+/// - `bpm <beats per minute>`
+/// - `length <seconds>` of the loop
+/// - `onset <seconds> <strength>` where something audible starts, strength goes from 0 to 1
+/// - `spawn <seconds> <lane> <colour>` an enemy for rhythm mode, lanes are counted from the top
+#[derive(TypeUuid, Default)]
+#[uuid = "2a9e4c1b-6d3f-4e8a-b5c7-9f1d0e2a3b4c"]
+pub struct BeatMap {
+    pub bpm: f64,
+    pub length: f64,
+    pub onsets: Vec<(f64, f32)>,
+    pub spawns: Vec<BeatSpawn>,
+}
+
+pub struct BeatSpawn {
+    pub time: f64,
+    pub lane: usize,
+    pub colour: Colour,
+}
+
+impl BeatMap {
+    fn parse(s: &str) -> Result<Self, bevy::asset::Error> {
+        let mut map = BeatMap::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || bevy::asset::Error::msg(format!("invalid beat map line `{line}`"));
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                ["bpm", bpm] => map.bpm = bpm.parse()?,
+                ["length", length] => map.length = length.parse()?,
+                ["onset", time, strength] => map.onsets.push((time.parse()?, strength.parse()?)),
+                ["spawn", time, lane, colour] => map.spawns.push(BeatSpawn {
+                    time: time.parse()?,
+                    lane: lane.parse()?,
+                    colour: Colour::from_name(colour).ok_or_else(invalid)?,
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if map.bpm <= 0.0 || map.length <= 0.0 {
+            return Err(bevy::asset::Error::msg("beat map needs a bpm and a length"));
+        }
+        Ok(map)
+    }
+}
+
+#[derive(Default)]
+pub struct BeatMapLoader;
+
+impl AssetLoader for BeatMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let map = BeatMap::parse(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["beats"]
+    }
+}
+
+/// how much the music is "hitting" right now, from 0 to 1.
+/// jumps up on every onset of the beat map and then fades out
+#[derive(Default)]
+pub struct MusicPulse(pub f32);
+
+pub fn update_music_pulse(
+    mut pulse: ResMut<MusicPulse>,
+    clock: Res<MusicClock>,
+    // only exists once loading is done
+    aa: Option<Res<AudioAssets>>,
+    maps: Res<Assets<BeatMap>>,
+    time: Res<Time>,
+) {
+    let map = match aa.and_then(|aa| maps.get(&aa.music_beats)) {
+        Some(m) => m,
+        None => return,
+    };
+
+    let position = clock.position(time.seconds_since_startup(), map.length);
+    pulse.0 = map
+        .onsets
+        .iter()
+        .map(|(at, strength)| {
+            let since = (position - at).rem_euclid(map.length) as f32;
+            strength * (-since * 6.0).exp()
+        })
+        .fold(0.0, f32::max);
+}
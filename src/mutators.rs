@@ -0,0 +1,218 @@
+use crate::*;
+
+/// optional rules picked before a run, each one changes the score multiplier
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    /// a single point of health
+    GlassCannon,
+    WideBeams,
+    FastEnemies,
+    /// only enemies that need more than one beam
+    NoPrimaries,
+    /// enemies come in on the lane opposite to the one they were meant for
+    MirrorLanes,
+    /// enemies take some damage from any of the beams they need
+    PartialDamage,
+    /// enemies heal when none of their beams are on them
+    Regenerating,
+}
+
+const ALL_MUTATORS: [Mutator; 7] = [
+    Mutator::GlassCannon,
+    Mutator::WideBeams,
+    Mutator::FastEnemies,
+    Mutator::NoPrimaries,
+    Mutator::MirrorLanes,
+    Mutator::PartialDamage,
+    Mutator::Regenerating,
+];
+
+impl Mutator {
+    fn key(&self) -> &'static str {
+        match self {
+            Mutator::GlassCannon => "mutator-glass-cannon",
+            Mutator::WideBeams => "mutator-wide-beams",
+            Mutator::FastEnemies => "mutator-fast-enemies",
+            Mutator::NoPrimaries => "mutator-no-primaries",
+            Mutator::MirrorLanes => "mutator-mirror-lanes",
+            Mutator::PartialDamage => "mutator-partial-damage",
+            Mutator::Regenerating => "mutator-regenerating",
+        }
+    }
+
+    fn multiplier(&self) -> f32 {
+        match self {
+            Mutator::GlassCannon => 3.0,
+            // makes the game easier
+            Mutator::WideBeams => 0.5,
+            Mutator::FastEnemies => 1.5,
+            Mutator::NoPrimaries => 1.5,
+            Mutator::MirrorLanes => 1.25,
+            // also makes it easier
+            Mutator::PartialDamage => 0.75,
+            Mutator::Regenerating => 1.25,
+        }
+    }
+
+    fn apply(&self, modifiers: &mut RunModifiers) {
+        match self {
+            Mutator::GlassCannon => modifiers.starting_health = 1,
+            Mutator::WideBeams => modifiers.beam_width = 2.0,
+            Mutator::FastEnemies => modifiers.enemy_speed = 1.5,
+            Mutator::NoPrimaries => modifiers.only_mixed = true,
+            Mutator::MirrorLanes => modifiers.mirror_lanes = true,
+            Mutator::PartialDamage => modifiers.partial_damage = true,
+            Mutator::Regenerating => modifiers.kill_decay = 0.5,
+        }
+        modifiers.score_multiplier *= self.multiplier();
+    }
+}
+
+/// the mutators that are on for the next endless or rhythm run
+#[derive(Default, Deref, DerefMut)]
+pub struct SelectedMutators(Vec<Mutator>);
+
+impl SelectedMutators {
+    pub fn modifiers(&self) -> RunModifiers {
+        let mut modifiers = RunModifiers::default();
+        self.apply(&mut modifiers);
+        modifiers
+    }
+
+    pub fn apply(&self, modifiers: &mut RunModifiers) {
+        for mutator in &self.0 {
+            mutator.apply(modifiers);
+        }
+    }
+
+    fn toggle(&mut self, mutator: Mutator) {
+        if let Some(i) = self.0.iter().position(|m| *m == mutator) {
+            self.0.remove(i);
+        } else {
+            self.0.push(mutator);
+        }
+    }
+
+    fn label(&self, mutator: Mutator) -> Vec<String> {
+        let check = if self.0.contains(&mutator) {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        vec![
+            check.to_string(),
+            mutator.key().to_string(),
+            mutator.multiplier().to_string(),
+        ]
+    }
+}
+
+#[derive(Component)]
+pub struct MutatorsStartButton;
+
+#[derive(Component)]
+pub struct MutatorsBackButton;
+
+#[derive(Component)]
+pub struct MutatorsMultiplierText;
+
+pub fn setup_mutators(mut commands: Commands, a: Res<GameAssets>, selected: Res<SelectedMutators>) {
+    let text = |size| {
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: a.font.clone(),
+                font_size: size,
+                color: Color::WHITE,
+            },
+        )
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::SpaceEvenly,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(CleanupMutators)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(text(60.0))
+                .insert(Localized::new("mutators-title"));
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for mutator in ALL_MUTATORS {
+                        let label = Localized::with_args("mutator-entry", selected.label(mutator));
+                        spawn_button(parent, &a, label, 30.0).insert(mutator);
+                    }
+                });
+
+            parent
+                .spawn_bundle(text(30.0))
+                .insert(Localized::with_args(
+                    "mutators-multiplier",
+                    vec![selected.modifiers().score_multiplier.to_string()],
+                ))
+                .insert(MutatorsMultiplierText);
+
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                spawn_button(parent, &a, Localized::new("mutators-back"), 40.0)
+                    .insert(MutatorsBackButton);
+                spawn_button(parent, &a, Localized::new("mutators-start"), 40.0)
+                    .insert(MutatorsStartButton);
+            });
+        });
+}
+
+pub fn mutators_screen(
+    mut state: ResMut<State<GameState>>,
+    mut selected: ResMut<SelectedMutators>,
+    toggles: Query<(&Interaction, &Mutator, &Children), Changed<Interaction>>,
+    start: Query<&Interaction, (Changed<Interaction>, With<MutatorsStartButton>)>,
+    back: Query<&Interaction, (Changed<Interaction>, With<MutatorsBackButton>)>,
+    mut labels: Query<&mut Localized, Without<MutatorsMultiplierText>>,
+    mut multiplier: Query<&mut Localized, With<MutatorsMultiplierText>>,
+) {
+    for (interaction, mutator, children) in &toggles {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        selected.toggle(*mutator);
+
+        for child in children {
+            if let Ok(mut text) = labels.get_mut(*child) {
+                text.args = selected.label(*mutator);
+            }
+        }
+        for mut text in &mut multiplier {
+            text.args = vec![selected.modifiers().score_multiplier.to_string()];
+        }
+    }
+
+    for interaction in &start {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Game).unwrap();
+        }
+    }
+    for interaction in &back {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Menu).unwrap();
+        }
+    }
+}
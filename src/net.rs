@@ -0,0 +1,844 @@
+use crate::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// how often the client says hello until the host answers, in seconds
+const HELLO_INTERVAL: f64 = 1.0;
+/// how often the host sends a `Start` or `End` again until the client acks it, in seconds
+const RESEND_INTERVAL: f64 = 0.25;
+
+/// something that moves packets between the host and the client
+pub trait Transport: Send + Sync {
+    fn send(&mut self, packet: &[u8]);
+    /// everything that arrived since the last call
+    fn receive(&mut self) -> Vec<Vec<u8>>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UdpTransport {
+    socket: std::net::UdpSocket,
+    /// the host learns who the client is from its first packet
+    peer: Option<std::net::SocketAddr>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UdpTransport {
+    pub fn host(addr: &str) -> std::io::Result<Self> {
+        let socket = std::net::UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer: None })
+    }
+
+    pub fn join(addr: &str) -> std::io::Result<Self> {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        let peer = std::net::ToSocketAddrs::to_socket_addrs(addr)?.next();
+        Ok(Self { socket, peer })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        if let Some(peer) = self.peer {
+            // packets get lost sometimes anyway, the next snapshot makes up for it
+            let _ = self.socket.send_to(packet, peer);
+        }
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        let mut packets = vec![];
+        let mut buf = [0; 65507];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    let peer = *self.peer.get_or_insert(from);
+                    if from == peer {
+                        packets.push(buf[..len].to_vec());
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                // windows reports it when the other side isn't listening yet
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    warn!("couldn't receive from the network: {e}");
+                    break;
+                }
+            }
+        }
+        packets
+    }
+}
+
+#[cfg(test)]
+type PacketQueue = std::sync::Arc<std::sync::Mutex<Vec<Vec<u8>>>>;
+
+/// two ends of an in-memory connection, for tests
+#[cfg(test)]
+pub struct LoopbackTransport {
+    inbox: PacketQueue,
+    outbox: PacketQueue,
+}
+
+#[cfg(test)]
+impl LoopbackTransport {
+    pub fn pair() -> (Self, Self) {
+        let a = PacketQueue::default();
+        let b = PacketQueue::default();
+        (
+            Self {
+                inbox: a.clone(),
+                outbox: b.clone(),
+            },
+            Self {
+                inbox: b,
+                outbox: a,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+impl Transport for LoopbackTransport {
+    fn send(&mut self, packet: &[u8]) {
+        self.outbox.lock().unwrap().push(packet.to_vec());
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.inbox.lock().unwrap())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    Offline,
+    /// runs the game, and the second player is whoever joins
+    Host,
+    /// shows what the host sends, and sends back its input
+    Client,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum NetMessage {
+    // client to host
+    Hello,
+    Input {
+        movement: Vec2,
+        aim: Vec2,
+        dash: bool,
+        shield: bool,
+    },
+    /// got the `Start` or `End` with this number
+    Ack(u32),
+    // host to client, `Start` and `End` are numbered and sent until they're acked
+    Start {
+        seq: u32,
+        level: Level,
+        /// the rest is so the client sets up the same run as the host
+        mode: GameMode,
+        modifiers: RunModifiers,
+        shared_health: bool,
+    },
+    Snapshot(Snapshot),
+    End {
+        seq: u32,
+    },
+}
+
+impl NetMessage {
+    fn seq(&self) -> Option<u32> {
+        match self {
+            NetMessage::Start { seq, .. } | NetMessage::End { seq } => Some(*seq),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NetPlayer {
+    pub index: usize,
+    pub position: Vec2,
+    pub beam: BeamColor,
+    pub shielded: bool,
+    /// seconds until the shield can be used again
+    pub shield_cooldown: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NetBeam {
+    pub color: BeamColor,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NetEnemy {
+    /// the entity on the host
+    pub id: u64,
+    pub position: Vec2,
+    pub colour: Colour,
+    pub damaged: bool,
+    pub progress: f32,
+    pub hitting: Vec<BeamColor>,
+}
+
+/// everything the client needs to draw a frame
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub players: Vec<NetPlayer>,
+    pub beams: Vec<NetBeam>,
+    pub enemies: Vec<NetEnemy>,
+    /// enemies that were killed and are dissolving on the host.
+    /// they stay in here for a while, so one lost snapshot doesn't matter
+    pub dying: Vec<u64>,
+    pub health: i8,
+    pub kills: usize,
+    /// seconds since the run started
+    pub elapsed: f64,
+}
+
+pub struct NetSession {
+    pub role: NetRole,
+    transport: Option<Box<dyn Transport>>,
+    /// whether anything has been heard from the other side
+    pub connected: bool,
+    /// on the host, a `Start` or `End` that hasn't been acked yet and when it was last sent
+    unacked: Option<(NetMessage, f64)>,
+    /// on the host the number of the last `Start` or `End`, on the client the last one handled
+    seq: Option<u32>,
+}
+
+impl NetSession {
+    /// `--host <address>` waits for a second player, `--join <address>` joins one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let arg = |flag: &str| {
+            args.iter()
+                .position(|a| a == flag)
+                .and_then(|i| args.get(i + 1))
+        };
+
+        let (role, transport) = if let Some(addr) = arg("--host") {
+            (NetRole::Host, UdpTransport::host(addr))
+        } else if let Some(addr) = arg("--join") {
+            (NetRole::Client, UdpTransport::join(addr))
+        } else {
+            return Self::offline();
+        };
+
+        match transport {
+            Ok(transport) => Self::new(role, Box::new(transport)),
+            Err(e) => {
+                warn!("couldn't open a socket, playing offline: {e}");
+                Self::offline()
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_args() -> Self {
+        Self::offline()
+    }
+
+    pub fn new(role: NetRole, transport: Box<dyn Transport>) -> Self {
+        Self {
+            role,
+            transport: Some(transport),
+            ..Self::offline()
+        }
+    }
+
+    fn offline() -> Self {
+        Self {
+            role: NetRole::Offline,
+            transport: None,
+            connected: false,
+            unacked: None,
+            seq: None,
+        }
+    }
+
+    /// index of the player controlled on this machine, the client plays the second one
+    pub fn local_player(&self) -> usize {
+        match self.role {
+            NetRole::Client => 1,
+            _ => 0,
+        }
+    }
+
+    fn send(&mut self, message: &NetMessage) {
+        if let Some(transport) = &mut self.transport {
+            match ron::to_string(message) {
+                Ok(s) => transport.send(s.as_bytes()),
+                Err(e) => warn!("couldn't serialize network message: {e}"),
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Vec<NetMessage> {
+        let packets = match &mut self.transport {
+            Some(transport) => transport.receive(),
+            None => return vec![],
+        };
+        let messages = packets
+            .iter()
+            .filter_map(|p| match ron::de::from_bytes(p) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    warn!("couldn't deserialize network message: {e}");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if !messages.is_empty() {
+            self.connected = true;
+        }
+        messages
+    }
+
+    /// sends a numbered `Start` or `End`, and keeps sending it until the client acks it
+    fn announce(&mut self, message: impl FnOnce(u32) -> NetMessage, now: f64) {
+        let seq = self.seq.map_or(0, |s| s.wrapping_add(1));
+        self.seq = Some(seq);
+        let message = message(seq);
+        self.send(&message);
+        self.unacked = Some((message, now));
+    }
+
+    fn resend_unacked(&mut self, now: f64) {
+        if let Some((message, at)) = self.unacked.take() {
+            if now - at >= RESEND_INTERVAL {
+                self.send(&message);
+                self.unacked = Some((message, now));
+            } else {
+                self.unacked = Some((message, at));
+            }
+        }
+    }
+}
+
+/// what the client's player is doing, applied on the host to `Controls::Remote`
+#[derive(Default)]
+pub struct RemoteInput {
+    pub movement: Vec2,
+    pub aim: Vec2,
+    pub dash: bool,
+    pub shield: bool,
+    /// the dash and shield buttons were pressed since the last frame. the client only sends
+    /// whether they're held, so a press isn't missed when several messages arrive at once
+    pub dash_pressed: bool,
+    pub shield_pressed: bool,
+}
+
+/// a `Start` for the run the host is playing
+fn start_message(
+    seq: u32,
+    level: &CurrentLevel,
+    mode: &GameMode,
+    modifiers: &RunModifiers,
+    coop: &CoopSettings,
+) -> NetMessage {
+    NetMessage::Start {
+        seq,
+        level: level.0.clone(),
+        mode: *mode,
+        modifiers: *modifiers,
+        shared_health: coop.shared_health,
+    }
+}
+
+pub fn host_network(
+    mut net: ResMut<NetSession>,
+    mut input: ResMut<RemoteInput>,
+    state: Res<State<GameState>>,
+    (level, mode, modifiers, coop): (
+        Res<CurrentLevel>,
+        Res<GameMode>,
+        Res<RunModifiers>,
+        Res<CoopSettings>,
+    ),
+    time: Res<Time>,
+) {
+    if net.role != NetRole::Host {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    input.dash_pressed = false;
+    input.shield_pressed = false;
+    for message in net.receive() {
+        match message {
+            // joining halfway through a run
+            NetMessage::Hello if *state.current() == GameState::Game => {
+                net.announce(
+                    |seq| start_message(seq, &level, &mode, &modifiers, &coop),
+                    now,
+                );
+            }
+            NetMessage::Ack(seq)
+                if net.unacked.as_ref().and_then(|(m, _)| m.seq()) == Some(seq) =>
+            {
+                net.unacked = None;
+            }
+            NetMessage::Input {
+                movement,
+                aim,
+                dash,
+                shield,
+            } => {
+                input.movement = movement;
+                input.aim = aim;
+                input.dash_pressed |= dash && !input.dash;
+                input.dash = dash;
+                input.shield_pressed |= shield && !input.shield;
+                input.shield = shield;
+            }
+            _ => {}
+        }
+    }
+    net.resend_unacked(now);
+}
+
+pub fn announce_run(
+    mut net: ResMut<NetSession>,
+    level: Res<CurrentLevel>,
+    mode: Res<GameMode>,
+    modifiers: Res<RunModifiers>,
+    coop: Res<CoopSettings>,
+    time: Res<Time>,
+) {
+    if net.role == NetRole::Host {
+        net.announce(
+            |seq| start_message(seq, &level, &mode, &modifiers, &coop),
+            time.seconds_since_startup(),
+        );
+    }
+}
+
+pub fn announce_end(mut net: ResMut<NetSession>, time: Res<Time>) {
+    if net.role == NetRole::Host {
+        net.announce(|seq| NetMessage::End { seq }, time.seconds_since_startup());
+    }
+}
+
+pub fn send_snapshot(
+    mut net: ResMut<NetSession>,
+    players: Query<(&Player, &Transform, &ClosestBeam, &Shield)>,
+    beams: Query<(&BeamColor, &Transform)>,
+    enemies: Query<(Entity, &Transform, &Colour, &Killable), With<Enemy>>,
+    dying: Query<Entity, With<Dying>>,
+    health: Res<PlayerHealth>,
+    kills: Res<EnemiesKilled>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+) {
+    if net.role != NetRole::Host || !net.connected {
+        return;
+    }
+
+    let snapshot = Snapshot {
+        players: players
+            .iter()
+            .map(|(player, trans, beam, shield)| NetPlayer {
+                index: player.index,
+                position: trans.translation.xy(),
+                beam: beam.0,
+                shielded: shield.is_up(),
+                shield_cooldown: shield.cooldown,
+            })
+            .collect(),
+        beams: beams
+            .iter()
+            .map(|(color, trans)| NetBeam {
+                color: *color,
+                translation: trans.translation,
+                rotation: trans.rotation,
+                scale: trans.scale,
+            })
+            .collect(),
+        enemies: enemies
+            .iter()
+            .map(|(entity, trans, colour, killable)| NetEnemy {
+                id: entity.to_bits(),
+                position: trans.translation.xy(),
+                colour: *colour,
+                damaged: killable.under_damage,
+                progress: killable.progress,
+                hitting: killable.hitting.clone(),
+            })
+            .collect(),
+        dying: dying.iter().map(|e| e.to_bits()).collect(),
+        health: health.health,
+        kills: kills.0,
+        elapsed: time.seconds_since_startup() - game_start.0,
+    };
+    net.send(&NetMessage::Snapshot(snapshot));
+}
+
+/// the last snapshot from the host that hasn't been applied yet
+#[derive(Default)]
+pub struct LatestSnapshot(Option<Snapshot>);
+
+/// enemies on the host, and the entities that show them here
+#[derive(Default)]
+pub struct RemoteEnemies(HashMap<u64, Entity>);
+
+pub fn client_network(
+    mut net: ResMut<NetSession>,
+    mut state: ResMut<State<GameState>>,
+    mut latest: ResMut<LatestSnapshot>,
+    mut current: ResMut<CurrentLevel>,
+    mut coop: ResMut<CoopSettings>,
+    mut mode: ResMut<GameMode>,
+    mut modifiers: ResMut<RunModifiers>,
+    time: Res<Time>,
+    mut last_hello: Local<Option<f64>>,
+) {
+    // the messages wait in the socket until everything is loaded
+    if net.role != NetRole::Client || *state.current() == GameState::Loading {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    if !net.connected && last_hello.is_none_or(|t| now - t > HELLO_INTERVAL) {
+        net.send(&NetMessage::Hello);
+        *last_hello = Some(now);
+    }
+
+    for message in net.receive() {
+        if let Some(seq) = message.seq() {
+            // acked every time, in case the last ack got lost
+            net.send(&NetMessage::Ack(seq));
+            if net.seq == Some(seq) {
+                continue;
+            }
+            net.seq = Some(seq);
+        }
+        match message {
+            NetMessage::Start {
+                level,
+                mode: host_mode,
+                modifiers: host_modifiers,
+                shared_health,
+                ..
+            } => {
+                // applied before game_setup and setup_ui run on entering Remote
+                current.0 = level;
+                *mode = host_mode;
+                *modifiers = host_modifiers;
+                coop.enabled = true;
+                coop.shared_health = shared_health;
+                latest.0 = None;
+                let _ = if *state.current() == GameState::Remote {
+                    state.restart()
+                } else {
+                    state.set(GameState::Remote)
+                };
+            }
+            NetMessage::Snapshot(snapshot) => latest.0 = Some(snapshot),
+            NetMessage::End { .. } if *state.current() == GameState::Remote => {
+                let _ = state.set(GameState::Menu);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn send_remote_input(
+    mut net: ResMut<NetSession>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mouse: Res<MousePos>,
+) {
+    let controls = Controls::KeyboardMouse;
+    net.send(&NetMessage::Input {
+        movement: controls.movement(&keys, &gamepads, &axes, &default()),
+        aim: mouse.pos,
+        dash: controls.dash_held(&keys, &buttons, &gamepads, &default()),
+        shield: controls.shield_held(&keys, &mouse_buttons, &buttons, &gamepads, &default()),
+    });
+}
+
+pub fn clear_remote_enemies(mut enemies: ResMut<RemoteEnemies>) {
+    enemies.0.clear();
+}
+
+pub fn apply_snapshot(
+    mut latest: ResMut<LatestSnapshot>,
+    mut players: Query<
+        (
+            &Player,
+            &mut Transform,
+            &mut Velocity,
+            &mut ClosestBeam,
+            &mut Shield,
+        ),
+        (Without<BeamColor>, Without<Enemy>),
+    >,
+    mut beams: Query<(&BeamColor, &mut Transform), (Without<Player>, Without<Enemy>)>,
+    mut enemies: Query<
+        (&mut Transform, &mut Killable),
+        (With<Enemy>, Without<Player>, Without<BeamColor>),
+    >,
+    mut known: ResMut<RemoteEnemies>,
+    mut spawner: EnemySpawner,
+    mut health: ResMut<PlayerHealth>,
+    mut kills: ResMut<EnemiesKilled>,
+    mut game_start: ResMut<GameStartTime>,
+    time: Res<Time>,
+) {
+    let snapshot = match latest.0.take() {
+        Some(s) => s,
+        None => return,
+    };
+
+    for (player, mut trans, mut velocity, mut beam, mut shield) in &mut players {
+        if let Some(p) = snapshot.players.iter().find(|p| p.index == player.index) {
+            // only for the animations
+            velocity.0 = (p.position - trans.translation.xy()) / time.delta_seconds().max(0.001);
+            trans.translation.x = p.position.x;
+            trans.translation.y = p.position.y;
+            if beam.0 != p.beam {
+                beam.0 = p.beam;
+            }
+            if shield.is_up() != p.shielded {
+                shield.active = if p.shielded { SHIELD_WINDOW } else { 0.0 };
+            }
+            if shield.cooldown != p.shield_cooldown {
+                shield.cooldown = p.shield_cooldown;
+            }
+        }
+    }
+
+    for (color, mut trans) in &mut beams {
+        if let Some(b) = snapshot.beams.iter().find(|b| b.color == *color) {
+            trans.translation = b.translation;
+            trans.rotation = b.rotation;
+            trans.scale = b.scale;
+        }
+    }
+
+    // enemies the host doesn't have anymore
+    known.0.retain(|id, entity| {
+        let alive = snapshot.enemies.iter().any(|e| e.id == *id);
+        if !alive {
+            // otherwise it reached a player
+            if snapshot.dying.contains(id) {
+                start_dying(&mut spawner.cmd, *entity);
+            } else {
+                spawner.cmd.entity(*entity).despawn_recursive();
+            }
+        }
+        alive
+    });
+    for enemy in &snapshot.enemies {
+        match known.0.get(&enemy.id).map(|e| enemies.get_mut(*e)) {
+            Some(Ok((mut trans, mut killable))) => {
+                trans.translation.x = enemy.position.x;
+                trans.translation.y = enemy.position.y;
+                killable.under_damage = enemy.damaged;
+                killable.progress = enemy.progress;
+                killable.hitting = enemy.hitting.clone();
+            }
+            // spawned last frame, the commands haven't gone through yet
+            Some(Err(_)) => {}
+            None => {
+                let entity = spawner.spawn(enemy.position, enemy.colour);
+                known.0.insert(enemy.id, entity);
+            }
+        }
+    }
+
+    health.health = snapshot.health;
+    kills.0 = snapshot.kills;
+    game_start.0 = time.seconds_since_startup() - snapshot.elapsed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn host_app(transport: LoopbackTransport) -> App {
+        let mut app = App::new();
+        app.insert_resource(NetSession::new(NetRole::Host, Box::new(transport)))
+            .insert_resource(Time::default())
+            .init_resource::<RemoteInput>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<GameMode>()
+            .init_resource::<RunModifiers>()
+            .init_resource::<CoopSettings>()
+            .add_state(GameState::Game)
+            .add_system(host_network);
+        app
+    }
+
+    fn client_app(transport: LoopbackTransport) -> App {
+        let mut app = App::new();
+        app.insert_resource(NetSession::new(NetRole::Client, Box::new(transport)))
+            .insert_resource(Time::default())
+            .init_resource::<LatestSnapshot>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<CoopSettings>()
+            .init_resource::<GameMode>()
+            .init_resource::<RunModifiers>()
+            .add_state(GameState::Menu)
+            .add_system(client_network);
+        app
+    }
+
+    fn state(app: &App) -> GameState {
+        app.world.resource::<State<GameState>>().current().clone()
+    }
+
+    #[test]
+    fn hello_gets_a_start() {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = host_app(a);
+        let mut client = client_app(b);
+        host.insert_resource(GameMode::Daily);
+        host.world.resource_mut::<RunModifiers>().score_multiplier = 1.5;
+        host.world.resource_mut::<CoopSettings>().shared_health = true;
+
+        client.update();
+        host.update();
+        client.update();
+        client.update();
+
+        assert_eq!(state(&client), GameState::Remote);
+        let coop = client.world.resource::<CoopSettings>();
+        assert!(coop.enabled);
+        assert!(coop.shared_health);
+        // the hud is built from these
+        assert_eq!(*client.world.resource::<GameMode>(), GameMode::Daily);
+        assert_eq!(
+            client.world.resource::<RunModifiers>().score_multiplier,
+            1.5
+        );
+
+        // the client acked it, so it isn't sent again
+        host.update();
+        assert!(host.world.resource::<NetSession>().unacked.is_none());
+    }
+
+    #[test]
+    fn lost_start_is_sent_again() {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = host_app(a);
+        let mut client = client_app(b);
+
+        client.update();
+        host.update();
+        // the start gets lost on the way
+        let mut net = client.world.resource_mut::<NetSession>();
+        net.transport.as_mut().unwrap().receive();
+
+        let later = Instant::now() + Duration::from_secs_f64(RESEND_INTERVAL * 2.0);
+        host.world.resource_mut::<Time>().update_with_instant(later);
+        host.update();
+        client.update();
+        client.update();
+
+        assert_eq!(state(&client), GameState::Remote);
+    }
+
+    #[test]
+    fn input_is_applied() {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = host_app(a);
+        let mut client = NetSession::new(NetRole::Client, Box::new(b));
+
+        client.send(&NetMessage::Input {
+            movement: Vec2::new(1.0, 0.0),
+            aim: Vec2::new(10.0, 20.0),
+            dash: true,
+            shield: false,
+        });
+        host.update();
+
+        let input = host.world.resource::<RemoteInput>();
+        assert_eq!(input.movement, Vec2::new(1.0, 0.0));
+        assert_eq!(input.aim, Vec2::new(10.0, 20.0));
+        assert!(input.dash);
+        assert!(input.dash_pressed);
+        assert!(!input.shield);
+    }
+
+    #[test]
+    fn holding_the_shield_presses_it_once() {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = host_app(a);
+        let mut client = NetSession::new(NetRole::Client, Box::new(b));
+        let input = |shield| NetMessage::Input {
+            movement: Vec2::ZERO,
+            aim: Vec2::ZERO,
+            dash: false,
+            shield,
+        };
+
+        client.send(&input(true));
+        client.send(&input(true));
+        host.update();
+        assert!(host.world.resource::<RemoteInput>().shield_pressed);
+
+        client.send(&input(true));
+        host.update();
+        assert!(!host.world.resource::<RemoteInput>().shield_pressed);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let (a, b) = LoopbackTransport::pair();
+        let mut host = NetSession::new(NetRole::Host, Box::new(a));
+        let mut client = NetSession::new(NetRole::Client, Box::new(b));
+
+        host.send(&NetMessage::Snapshot(Snapshot {
+            players: vec![NetPlayer {
+                index: 1,
+                position: Vec2::new(-500.0, 40.0),
+                beam: BeamColor::Blue,
+                shielded: true,
+                shield_cooldown: 1.5,
+            }],
+            beams: vec![],
+            enemies: vec![NetEnemy {
+                id: 42,
+                position: Vec2::new(300.0, 0.0),
+                colour: Colour::Cyan,
+                damaged: false,
+                progress: 0.5,
+                hitting: vec![BeamColor::Green],
+            }],
+            dying: vec![7],
+            health: 12,
+            kills: 3,
+            elapsed: 9.5,
+        }));
+
+        let mut messages = client.receive();
+        assert!(client.connected);
+        let snapshot = match messages.pop() {
+            Some(NetMessage::Snapshot(s)) => s,
+            _ => panic!("expected a snapshot"),
+        };
+        assert_eq!(snapshot.players[0].index, 1);
+        assert_eq!(snapshot.players[0].position, Vec2::new(-500.0, 40.0));
+        assert!(snapshot.players[0].beam == BeamColor::Blue);
+        assert!(snapshot.players[0].shielded);
+        assert_eq!(snapshot.players[0].shield_cooldown, 1.5);
+        let enemy = &snapshot.enemies[0];
+        assert_eq!(enemy.id, 42);
+        assert!(enemy.colour == Colour::Cyan);
+        assert_eq!(enemy.progress, 0.5);
+        assert!(enemy.hitting == vec![BeamColor::Green]);
+        assert_eq!(snapshot.dying, vec![7]);
+        assert_eq!(snapshot.health, 12);
+        assert_eq!(snapshot.kills, 3);
+        assert_eq!(snapshot.elapsed, 9.5);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::*;
+
+/// colours used for beams and enemies. the colour-blind variants are picked so
+/// every `Colour` stays distinguishable, instead of being physically accurate mixes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Normal,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl Palette {
+    pub fn next(&self) -> Self {
+        match self {
+            Palette::Normal => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::Normal,
+        }
+    }
+
+    /// locale key for the name of the palette
+    pub fn key(&self) -> &'static str {
+        match self {
+            Palette::Normal => "palette-normal",
+            Palette::Deuteranopia => "palette-deuteranopia",
+            Palette::Protanopia => "palette-protanopia",
+            Palette::Tritanopia => "palette-tritanopia",
+        }
+    }
+
+    pub fn beam(&self, beam: BeamColor) -> Color {
+        self.colour(beam.colour())
+    }
+
+    pub fn colour(&self, colour: Colour) -> Color {
+        use Colour::*;
+        let [r, g, b] = match self {
+            Palette::Normal => match colour {
+                Red => [1.0, 0.0, 0.0],
+                Green => [0.0, 1.0, 0.0],
+                Blue => [0.0, 0.0, 1.0],
+                Yellow => [1.0, 1.0, 0.0],
+                Magenta => [1.0, 0.0, 1.0],
+                Cyan => [0.0, 1.0, 1.0],
+                White => [1.0, 1.0, 1.0],
+            },
+            Palette::Deuteranopia => match colour {
+                Red => [0.9, 0.6, 0.0],
+                Green => [0.35, 0.7, 0.9],
+                Blue => [0.25, 0.2, 0.85],
+                Yellow => [0.95, 0.9, 0.25],
+                Magenta => [0.8, 0.47, 0.65],
+                Cyan => [0.0, 0.45, 0.7],
+                White => [1.0, 1.0, 1.0],
+            },
+            Palette::Protanopia => match colour {
+                Red => [1.0, 0.75, 0.1],
+                Green => [0.35, 0.7, 0.9],
+                Blue => [0.2, 0.2, 0.75],
+                Yellow => [1.0, 0.95, 0.6],
+                Magenta => [0.6, 0.4, 0.8],
+                Cyan => [0.0, 0.45, 0.7],
+                White => [1.0, 1.0, 1.0],
+            },
+            Palette::Tritanopia => match colour {
+                Red => [0.9, 0.15, 0.15],
+                Green => [0.0, 0.6, 0.6],
+                Blue => [0.95, 0.55, 0.75],
+                Yellow => [0.55, 0.35, 0.1],
+                Magenta => [0.6, 0.0, 0.3],
+                Cyan => [0.6, 0.9, 0.9],
+                White => [1.0, 1.0, 1.0],
+            },
+        };
+        Color::rgb(r, g, b)
+    }
+}
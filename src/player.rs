@@ -1,62 +1,185 @@
 use crate::*;
 
+/// tuning for how the players move
+pub struct MovementConfig {
+    /// in px/s
+    pub max_speed: f32,
+    /// how fast the players speed up while moving, in px/s²
+    pub acceleration: f32,
+    /// how fast the players slow down when they stop moving, in px/s²
+    pub friction: f32,
+    pub dash_speed: f32,
+    /// how long a dash lasts, in seconds
+    pub dash_duration: f32,
+    pub dash_cooldown: f32,
+    /// how long a player can't be hurt after getting hit, in seconds
+    pub invulnerability: f32,
+    /// how often the sprite blinks while invulnerable, in seconds
+    pub blink_interval: f32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: 150.0,
+            acceleration: 1200.0,
+            friction: 900.0,
+            dash_speed: 600.0,
+            dash_duration: 0.12,
+            dash_cooldown: 1.0,
+            invulnerability: 1.0,
+            blink_interval: 0.1,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Velocity(pub Vec2);
+
+#[derive(Component, Default)]
+pub struct Dash {
+    /// seconds left of the current dash
+    active: f32,
+    /// seconds until the next dash
+    cooldown: f32,
+}
+
 pub fn move_player(
-    mut query: Query<(&mut Transform, &mut AnimationTimer), With<Player>>,
+    mut query: Query<(&mut Transform, &mut Velocity, &mut Dash, &Controls), With<Player>>,
     input: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
     time: Res<Time>,
+    level: Res<CurrentLevel>,
+    remote: Res<RemoteInput>,
+    config: Res<MovementConfig>,
 ) {
-    let mut diff = Vec2::ZERO;
-    if input.pressed(KeyCode::W) {
-        diff += Vec2::Y;
-    }
-    if input.pressed(KeyCode::S) {
-        diff -= Vec2::Y;
-    }
-    if input.pressed(KeyCode::A) {
-        diff -= Vec2::X;
-    }
-    if input.pressed(KeyCode::D) {
-        diff += Vec2::X;
+    let dt = time.delta_seconds();
+    for (mut trans, mut velocity, mut dash, controls) in &mut query {
+        let dir = controls
+            .movement(&input, &gamepads, &axes, &remote)
+            .clamp_length_max(1.0);
+
+        dash.cooldown -= dt;
+        if dash.active > 0.0 {
+            // keep going in the same direction until the dash is over
+            dash.active -= dt;
+        } else if dash.cooldown <= 0.0
+            && dir != Vec2::ZERO
+            && controls.dash(&input, &buttons, &gamepads, &remote)
+        {
+            velocity.0 = dir.normalize() * config.dash_speed;
+            dash.active = config.dash_duration;
+            dash.cooldown = config.dash_cooldown;
+        } else {
+            let rate = if dir == Vec2::ZERO {
+                config.friction
+            } else {
+                config.acceleration
+            };
+            let diff = dir * config.max_speed - velocity.0;
+            velocity.0 += diff.clamp_length_max(rate * dt);
+        }
+
+        let wanted = trans.translation.xy() + velocity.0 * dt;
+        let pos = wanted.clamp(level.player_min, level.player_max);
+        // stop against the edges instead of pushing into them
+        if pos.x != wanted.x {
+            velocity.0.x = 0.0;
+        }
+        if pos.y != wanted.y {
+            velocity.0.y = 0.0;
+        }
+        trans.translation.x = pos.x;
+        trans.translation.y = pos.y;
     }
-    diff *= 150.0 * time.delta_seconds();
+}
 
-    for (mut trans, mut anim) in &mut query {
-        anim.paused = diff.length_squared() < 0.1;
+/// a player that was just hit and can't be hurt for a bit
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
 
-        trans.translation.y = (diff.y + trans.translation.y).clamp(-120.0, 140.0);
-        trans.translation.x = (diff.x + trans.translation.x).clamp(-600.0, -525.0);
+pub fn blink_invulnerable_players(
+    mut cmd: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+    config: Res<MovementConfig>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut query {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            sprite.color.set_a(1.0);
+            cmd.entity(entity).remove::<Invulnerable>();
+        } else {
+            let blink = (invulnerable.0.elapsed_secs() / config.blink_interval) as u32 % 2;
+            sprite.color.set_a(if blink == 0 { 0.3 } else { 1.0 });
+        }
     }
 }
 
+pub const STARTING_HEALTH: i8 = 30;
+
+/// so the players can tell each other apart in co-op
+pub const PLAYER_TINTS: [Color; 2] = [Color::WHITE, Color::rgb(0.6, 0.8, 1.0)];
+
+/// health of the whole team, the run ends when it runs out
 pub struct PlayerHealth {
     pub health: i8,
+    /// what it started at
+    pub max: i8,
 }
 
+/// a player's own health, when co-op players don't share it
+#[derive(Component)]
+pub struct Health(pub i8);
+
 pub fn end_game_if_health_is_0(health: Res<PlayerHealth>, mut state: ResMut<State<GameState>>) {
     if health.health <= 0 {
         state.set(GameState::Death).unwrap();
     }
 }
 
-pub fn change_player_sprite(
-    mut query: Query<(&mut Handle<TextureAtlas>, &Transform), With<Player>>,
+/// picks the player's clip from what is going on around them
+pub fn update_player_animation(
+    mut players: Query<(Entity, &mut Animator, &Transform, &Velocity), With<Player>>,
+    hurt: Query<(), (With<Player>, Added<Invulnerable>)>,
     enemies: Query<&Transform, (Without<Player>, With<Enemy>)>,
-    a: Res<PlayerAssets>,
+    mut kills: EventReader<EnemyDead>,
 ) {
-    let (mut handle, player) = query.single_mut();
+    let happy = kills.iter().count() > 0;
+    for (entity, mut animator, player, velocity) in &mut players {
+        if hurt.contains(entity) {
+            animator.paused = false;
+            animator.play("hurt");
+            continue;
+        }
+        if animator.busy {
+            continue;
+        }
+        if happy {
+            animator.paused = false;
+            animator.play("happy");
+            continue;
+        }
 
-    let mut min_dis = f32::MAX;
-    for enemy in &enemies {
-        min_dis = min_dis.min(enemy.translation.xy().distance(player.translation.xy()));
-    }
+        let mut min_dis = f32::MAX;
+        for enemy in &enemies {
+            min_dis = min_dis.min(enemy.translation.xy().distance(player.translation.xy()));
+        }
 
-    *handle = if min_dis < 100.0 {
-        a.player_sad.clone()
-    } else if min_dis < 400.0 {
-        a.player_neutral.clone()
-    } else {
-        a.player.clone()
-    };
+        let moving = velocity.0.length_squared() >= 100.0;
+        animator.play(if min_dis < 100.0 {
+            "sad"
+        } else if min_dis < 400.0 {
+            "worried"
+        } else if moving {
+            "walk"
+        } else {
+            "idle"
+        });
+        animator.paused = !moving;
+    }
 }
 
 pub struct PlayerAssets {
@@ -69,27 +192,27 @@ impl FromWorld for PlayerAssets {
     fn from_world(world: &mut World) -> Self {
         let cell = world.cell();
         let mut textures = cell.get_resource_mut::<Assets<TextureAtlas>>().unwrap();
+        let sprites = cell.get_resource::<Assets<Aseprite>>().unwrap();
 
         let game_assets = cell
             .get_resource::<GameAssets>()
             .expect("Failed to get GameAssets");
+        let aseprite = sprites
+            .get(&game_assets.player)
+            .expect("Failed to get player.aseprite");
 
-        let player = textures.add(TextureAtlas::from_grid(
-            game_assets.player.clone(),
-            Vec2::new(32.0, 48.0),
-            8,
-            1,
-        ));
+        let player = aseprite.atlas.clone();
+        // the moods don't have their own aseprite files, but they're laid out the same way
         let player_neutral = textures.add(TextureAtlas::from_grid(
             game_assets.player_neutral.clone(),
-            Vec2::new(32.0, 48.0),
-            8,
+            aseprite.frame_size,
+            aseprite.frames,
             1,
         ));
         let player_sad = textures.add(TextureAtlas::from_grid(
             game_assets.player_sad.clone(),
-            Vec2::new(32.0, 48.0),
-            8,
+            aseprite.frame_size,
+            aseprite.frames,
             1,
         ));
         Self {
@@ -0,0 +1,185 @@
+use crate::*;
+
+/// how far from a beat a kill can be, in seconds, for each judgement
+const PERFECT_WINDOW: f64 = 0.07;
+const GOOD_WINDOW: f64 = 0.15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    /// enemies come from the music's beat map, and kills are judged by how close to the beat they are
+    Rhythm,
+    /// a level from the campaign, with an objective
+    Campaign,
+    /// a seeded run with modifiers that change every day
+    Daily,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Judgement {
+    Perfect,
+    Good,
+    Miss,
+}
+
+impl Judgement {
+    fn from_offset(offset: f64) -> Self {
+        if offset <= PERFECT_WINDOW {
+            Judgement::Perfect
+        } else if offset <= GOOD_WINDOW {
+            Judgement::Good
+        } else {
+            Judgement::Miss
+        }
+    }
+
+    fn points(&self) -> u32 {
+        match self {
+            Judgement::Perfect => 300,
+            Judgement::Good => 100,
+            Judgement::Miss => 10,
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Judgement::Perfect => "judgement-perfect",
+            Judgement::Good => "judgement-good",
+            Judgement::Miss => "judgement-miss",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Judgement::Perfect => Color::rgb(1.0, 0.9, 0.3),
+            Judgement::Good => Color::rgb(0.5, 0.9, 1.0),
+            Judgement::Miss => Color::GRAY,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RhythmScore {
+    pub score: u32,
+}
+
+/// queues the beat map's spawns a bit before they are due, so their warnings show up in time
+pub fn plan_rhythm_spawns(
+    mode: Res<GameMode>,
+    mut queue: ResMut<SpawnQueue>,
+    clock: Res<MusicClock>,
+    aa: Res<AudioAssets>,
+    maps: Res<Assets<BeatMap>>,
+    time: Res<Time>,
+    game_start: Res<GameStartTime>,
+    level: Res<CurrentLevel>,
+    mut planned_until: Local<f64>,
+    mut spawner: WarningSpawner,
+) {
+    if *mode != GameMode::Rhythm {
+        return;
+    }
+    let map = match maps.get(&aa.music_beats) {
+        Some(m) => m,
+        None => return,
+    };
+
+    let now = time.seconds_since_startup();
+    // new run, don't plan anything from before it started
+    if *planned_until < game_start.0 {
+        *planned_until = now;
+    }
+    let until = now + SPAWN_WARNING_SECONDS;
+
+    // go through every loop of the track that overlaps the window
+    let first_loop = ((*planned_until - clock.started_at) / map.length).floor() as i64;
+    let last_loop = ((until - clock.started_at) / map.length).floor() as i64;
+    for l in first_loop..=last_loop {
+        let loop_start = clock.started_at + l as f64 * map.length;
+        for spawn in &map.spawns {
+            let at = loop_start + spawn.time;
+            if at > *planned_until && at <= until {
+                let planned = spawner.plan(level.lane(spawn.lane), spawn.colour, at);
+                queue.push_back(planned);
+            }
+        }
+    }
+
+    *planned_until = until;
+}
+
+pub fn judge_rhythm_kills(
+    mut cmd: Commands,
+    mode: Res<GameMode>,
+    mut enemy_death: EventReader<EnemyDead>,
+    mut score: ResMut<RhythmScore>,
+    clock: Res<MusicClock>,
+    time: Res<Time>,
+    a: Res<GameAssets>,
+    modifiers: Res<RunModifiers>,
+) {
+    if *mode != GameMode::Rhythm {
+        enemy_death.clear();
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    let offset = clock.distance_to_beat(now);
+    for dead in enemy_death.iter() {
+        let judgement = Judgement::from_offset(offset);
+        score.score += (judgement.points() as f32 * modifiers.score_multiplier).round() as u32;
+
+        cmd.spawn_bundle(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: a.font.clone(),
+                    font_size: 30.0,
+                    color: judgement.color(),
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            transform: Transform::from_translation((dead.position + Vec2::Y * 50.0).extend(10.0)),
+            ..default()
+        })
+        .insert(Localized::new(judgement.key()))
+        .insert(JudgementPopup { spawned_at: now })
+        .insert(CleanupGame);
+    }
+}
+
+#[derive(Component)]
+pub struct JudgementPopup {
+    spawned_at: f64,
+}
+
+pub fn animate_judgement_popups(
+    mut cmd: Commands,
+    mut popups: Query<(Entity, &JudgementPopup, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    const LIFETIME: f32 = 0.6;
+
+    for (entity, popup, mut trans, mut text) in &mut popups {
+        let t = (time.seconds_since_startup() - popup.spawned_at) as f32 / LIFETIME;
+        if t >= 1.0 {
+            cmd.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        trans.translation.y += 40.0 * time.delta_seconds();
+        text.sections[0].style.color.set_a(1.0 - t);
+    }
+}
+
+pub fn record_rhythm_score(
+    mode: Res<GameMode>,
+    score: Res<RhythmScore>,
+    mut save: ResMut<SaveData>,
+) {
+    if *mode == GameMode::Rhythm {
+        save.add_rhythm_score(score.score);
+        save.store();
+    }
+}
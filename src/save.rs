@@ -0,0 +1,123 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+const LEADERBOARD_SIZE: usize = 10;
+
+/// everything that is kept between sessions
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SaveData {
+    /// best rhythm mode scores, highest first
+    pub rhythm_leaderboard: Vec<u32>,
+    /// best stars for each campaign level, 0 for the ones that haven't been completed
+    pub campaign_stars: Vec<u8>,
+    /// best score in today's daily challenge
+    pub daily_best: Option<DailyBest>,
+    /// spent in the shop between runs
+    pub shards: u32,
+    pub upgrades: Upgrades,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct DailyBest {
+    /// see `today()`
+    pub day: u64,
+    pub score: usize,
+}
+
+impl SaveData {
+    pub fn load() -> Self {
+        read_save()
+            .and_then(|s| match ron::from_str(&s) {
+                Ok(save) => Some(save),
+                Err(e) => {
+                    warn!("couldn't read save file, starting from scratch: {e}");
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn store(&self) {
+        match ron::ser::to_string_pretty(self, default()) {
+            Ok(s) => write_save(&s),
+            Err(e) => warn!("couldn't serialize save file: {e}"),
+        }
+    }
+
+    pub fn add_rhythm_score(&mut self, score: u32) {
+        self.rhythm_leaderboard.push(score);
+        self.rhythm_leaderboard.sort_unstable_by(|a, b| b.cmp(a));
+        self.rhythm_leaderboard.truncate(LEADERBOARD_SIZE);
+    }
+
+    /// scores from previous days don't count
+    pub fn add_daily_score(&mut self, day: u64, score: usize) {
+        match &mut self.daily_best {
+            Some(best) if best.day == day => best.score = best.score.max(score),
+            _ => self.daily_best = Some(DailyBest { day, score }),
+        }
+    }
+
+    pub fn level_stars(&self, level: usize) -> u8 {
+        self.campaign_stars.get(level).copied().unwrap_or(0)
+    }
+
+    /// the first level is always open, the rest need the one before to be completed
+    pub fn is_campaign_unlocked(&self, level: usize) -> bool {
+        level == 0 || self.level_stars(level - 1) > 0
+    }
+
+    pub fn record_campaign_stars(&mut self, level: usize, stars: u8) {
+        if self.campaign_stars.len() <= level {
+            self.campaign_stars.resize(level + 1, 0);
+        }
+        self.campaign_stars[level] = self.campaign_stars[level].max(stars);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_path() -> std::path::PathBuf {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(std::path::PathBuf::from)
+        .unwrap_or_default();
+    home.join(".luminity").join("save.ron")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_save() -> Option<String> {
+    std::fs::read_to_string(save_path()).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_save(data: &str) {
+    let path = save_path();
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, data));
+    if let Err(e) = result {
+        warn!("couldn't write save file to {}: {e}", path.display());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "luminity-save";
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_save() -> Option<String> {
+    storage()?.get_item(STORAGE_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_save(data: &str) {
+    if let Some(Err(e)) = storage().map(|s| s.set_item(STORAGE_KEY, data)) {
+        warn!("couldn't write save to local storage: {e:?}");
+    }
+}
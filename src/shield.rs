@@ -0,0 +1,95 @@
+use crate::*;
+
+/// how long the shield stays up, in seconds. enemies have to arrive in this window to be parried
+pub const SHIELD_WINDOW: f32 = 0.25;
+pub const SHIELD_COOLDOWN: f32 = 2.0;
+/// how far a parried enemy gets pushed away
+const SHIELD_KNOCKBACK: f32 = 200.0;
+const SHIELD_RADIUS: f32 = 40.0;
+
+#[derive(Component, Default)]
+pub struct Shield {
+    /// seconds left with the shield up
+    pub active: f32,
+    /// seconds until it can be used again
+    pub cooldown: f32,
+}
+
+impl Shield {
+    pub fn is_up(&self) -> bool {
+        self.active > 0.0
+    }
+
+    /// from 0 right after using it to 1 when it's ready again
+    pub fn charge(&self) -> f32 {
+        1.0 - (self.cooldown / SHIELD_COOLDOWN).clamp(0.0, 1.0)
+    }
+
+    /// pushes an enemy that touched the player back out
+    pub fn parry(player: Vec2, enemy: &mut Transform) {
+        let away = (enemy.translation.xy() - player).try_normalize();
+        let pos = enemy.translation.xy() + away.unwrap_or(Vec2::X) * SHIELD_KNOCKBACK;
+        enemy.translation.x = pos.x;
+        enemy.translation.y = pos.y;
+    }
+}
+
+/// the bubble around a player while the shield is up
+#[derive(Component)]
+pub struct ShieldBubble;
+
+pub fn add_shield_bubbles(
+    mut cmd: Commands,
+    players: Query<Entity, Added<Shield>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for player in &players {
+        let bubble = cmd
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: meshes.add(shape::Circle::new(SHIELD_RADIUS).into()).into(),
+                material: materials.add(Color::rgba(0.6, 0.9, 1.0, 0.35).into()),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(ShieldBubble)
+            .id();
+        cmd.entity(player).add_child(bubble);
+    }
+}
+
+pub fn use_shield(
+    mut players: Query<(&Controls, &mut Shield)>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    remote: Res<RemoteInput>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (controls, mut shield) in &mut players {
+        shield.active -= dt;
+        shield.cooldown -= dt;
+        if shield.cooldown <= 0.0 && controls.shield(&keys, &mouse, &buttons, &gamepads, &remote) {
+            shield.active = SHIELD_WINDOW;
+            shield.cooldown = SHIELD_COOLDOWN;
+        }
+    }
+}
+
+pub fn show_shield_bubbles(
+    players: Query<(&Shield, &Children), Changed<Shield>>,
+    mut bubbles: Query<&mut Visibility, With<ShieldBubble>>,
+) {
+    for (shield, children) in &players {
+        for child in children {
+            if let Ok(mut visibility) = bubbles.get_mut(*child) {
+                if visibility.is_visible != shield.is_up() {
+                    visibility.is_visible = shield.is_up();
+                }
+            }
+        }
+    }
+}
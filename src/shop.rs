@@ -0,0 +1,251 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// permanent upgrades bought with shards between runs
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Upgrade {
+    Health,
+    KillSpeed,
+    BeamLength,
+    /// enemies also come in on an extra lane, for more points
+    ExtraLane,
+}
+
+/// the kill speed upgrade never takes the kill time below this, in seconds
+const MIN_KILL_TIME: f32 = 0.5;
+
+const ALL_UPGRADES: [Upgrade; 4] = [
+    Upgrade::Health,
+    Upgrade::KillSpeed,
+    Upgrade::BeamLength,
+    Upgrade::ExtraLane,
+];
+
+impl Upgrade {
+    fn key(&self) -> &'static str {
+        match self {
+            Upgrade::Health => "upgrade-health",
+            Upgrade::KillSpeed => "upgrade-kill-speed",
+            Upgrade::BeamLength => "upgrade-beam-length",
+            Upgrade::ExtraLane => "upgrade-extra-lane",
+        }
+    }
+
+    fn max_level(&self) -> u8 {
+        match self {
+            Upgrade::ExtraLane => 1,
+            _ => 3,
+        }
+    }
+
+    /// shards for the next level, `None` when it's maxed out
+    fn cost(&self, level: u8) -> Option<u32> {
+        if level >= self.max_level() {
+            return None;
+        }
+        let base = match self {
+            Upgrade::Health => 20,
+            Upgrade::KillSpeed => 30,
+            Upgrade::BeamLength => 25,
+            Upgrade::ExtraLane => 60,
+        };
+        Some(base * (level as u32 + 1))
+    }
+
+    fn apply(&self, level: u8, modifiers: &mut RunModifiers) {
+        // the save file can say anything
+        let level = level.min(self.max_level()) as f32;
+        match self {
+            Upgrade::Health => {
+                modifiers.starting_health =
+                    modifiers.starting_health.saturating_add(5 * level as i8)
+            }
+            Upgrade::KillSpeed => {
+                modifiers.kill_time = (modifiers.kill_time - 0.25 * level).max(MIN_KILL_TIME)
+            }
+            Upgrade::BeamLength => modifiers.beam_length += 0.15 * level,
+            Upgrade::ExtraLane if level > 0.0 => {
+                modifiers.extra_lane = true;
+                modifiers.score_multiplier *= 1.25;
+            }
+            Upgrade::ExtraLane => {}
+        }
+    }
+}
+
+/// how many times each upgrade has been bought
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Upgrades {
+    pub health: u8,
+    pub kill_speed: u8,
+    pub beam_length: u8,
+    pub extra_lane: u8,
+}
+
+impl Upgrades {
+    fn level_mut(&mut self, upgrade: Upgrade) -> &mut u8 {
+        match upgrade {
+            Upgrade::Health => &mut self.health,
+            Upgrade::KillSpeed => &mut self.kill_speed,
+            Upgrade::BeamLength => &mut self.beam_length,
+            Upgrade::ExtraLane => &mut self.extra_lane,
+        }
+    }
+
+    fn level(&self, upgrade: Upgrade) -> u8 {
+        match upgrade {
+            Upgrade::Health => self.health,
+            Upgrade::KillSpeed => self.kill_speed,
+            Upgrade::BeamLength => self.beam_length,
+            Upgrade::ExtraLane => self.extra_lane,
+        }
+    }
+
+    pub fn apply(&self, modifiers: &mut RunModifiers) {
+        for upgrade in ALL_UPGRADES {
+            upgrade.apply(self.level(upgrade), modifiers);
+        }
+    }
+
+    fn label(&self, upgrade: Upgrade) -> Vec<String> {
+        let level = self.level(upgrade);
+        let cost = match upgrade.cost(level) {
+            Some(cost) => cost.to_string(),
+            None => "shop-maxed".to_string(),
+        };
+        vec![
+            upgrade.key().to_string(),
+            level.to_string(),
+            upgrade.max_level().to_string(),
+            cost,
+        ]
+    }
+}
+
+/// shards earned in a run, one per kill
+pub fn earn_shards(kills: Res<EnemiesKilled>, test: Res<TestPlay>, mut save: ResMut<SaveData>) {
+    // made up levels could hand out free shards
+    if test.0 || kills.0 == 0 {
+        return;
+    }
+    save.shards += kills.0 as u32;
+    save.store();
+}
+
+#[derive(Component)]
+pub struct ShopStartButton;
+
+#[derive(Component)]
+pub struct ShopBackButton;
+
+#[derive(Component)]
+pub struct ShardsText;
+
+pub fn setup_shop(mut commands: Commands, a: Res<GameAssets>, save: Res<SaveData>) {
+    let text = |size| {
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: a.font.clone(),
+                font_size: size,
+                color: Color::WHITE,
+            },
+        )
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::SpaceEvenly,
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(CleanupShop)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(text(60.0))
+                .insert(Localized::new("shop-title"));
+
+            parent
+                .spawn_bundle(text(30.0))
+                .insert(Localized::with_args(
+                    "shop-shards",
+                    vec![save.shards.to_string()],
+                ))
+                .insert(ShardsText);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for upgrade in ALL_UPGRADES {
+                        let label =
+                            Localized::with_args("upgrade-entry", save.upgrades.label(upgrade));
+                        spawn_button(parent, &a, label, 30.0).insert(upgrade);
+                    }
+                });
+
+            parent.spawn_bundle(button_row()).with_children(|parent| {
+                spawn_button(parent, &a, Localized::new("shop-back"), 40.0).insert(ShopBackButton);
+                spawn_button(parent, &a, Localized::new("shop-start"), 40.0)
+                    .insert(ShopStartButton);
+            });
+        });
+}
+
+pub fn shop_screen(
+    mut state: ResMut<State<GameState>>,
+    mut save: ResMut<SaveData>,
+    buy: Query<(&Interaction, &Upgrade, &Children), Changed<Interaction>>,
+    start: Query<&Interaction, (Changed<Interaction>, With<ShopStartButton>)>,
+    back: Query<&Interaction, (Changed<Interaction>, With<ShopBackButton>)>,
+    mut labels: Query<&mut Localized, Without<ShardsText>>,
+    mut shards: Query<&mut Localized, With<ShardsText>>,
+) {
+    for (interaction, upgrade, children) in &buy {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let level = save.upgrades.level(*upgrade);
+        let cost = match upgrade.cost(level) {
+            Some(cost) if cost <= save.shards => cost,
+            _ => continue,
+        };
+        save.shards -= cost;
+        *save.upgrades.level_mut(*upgrade) += 1;
+        save.store();
+
+        for child in children {
+            if let Ok(mut text) = labels.get_mut(*child) {
+                text.args = save.upgrades.label(*upgrade);
+            }
+        }
+        for mut text in &mut shards {
+            text.args = vec![save.shards.to_string()];
+        }
+    }
+
+    for interaction in &start {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Game).unwrap();
+        }
+    }
+    for interaction in &back {
+        if *interaction == Interaction::Clicked {
+            state.set(GameState::Menu).unwrap();
+        }
+    }
+}
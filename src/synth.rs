@@ -0,0 +1,299 @@
+use crate::*;
+use bevy::{audio::Decodable, reflect::TypeUuid};
+use std::{
+    f32::consts::TAU,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const SAMPLE_RATE: u32 = 44100;
+const DT: f32 = 1.0 / SAMPLE_RATE as f32;
+
+#[derive(Clone, Copy)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+}
+
+#[derive(Clone, Copy)]
+pub struct Oscillator {
+    waveform: Waveform,
+    /// from 0 to 1
+    phase: f32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform) -> Self {
+        Self {
+            waveform,
+            phase: 0.0,
+        }
+    }
+
+    pub fn next(&mut self, frequency: f32) -> f32 {
+        let p = self.phase;
+        self.phase = (self.phase + frequency * DT).fract();
+        match self.waveform {
+            Waveform::Sine => (p * TAU).sin(),
+            Waveform::Square => {
+                if p < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Saw => p * 2.0 - 1.0,
+            Waveform::Triangle => 1.0 - (p * 4.0 - 2.0).abs(),
+        }
+    }
+}
+
+/// a linear attack, decay, sustain, release envelope. times are in seconds
+#[derive(Clone, Copy)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    /// how long the sustain level is held before releasing
+    pub hold: f32,
+    pub release: f32,
+}
+
+impl Envelope {
+    pub fn length(&self) -> f32 {
+        self.attack + self.decay + self.hold + self.release
+    }
+
+    pub fn amplitude(&self, t: f32) -> f32 {
+        let mut t = t;
+        if t < self.attack {
+            return t / self.attack;
+        }
+        t -= self.attack;
+        if t < self.decay {
+            return 1.0 - (1.0 - self.sustain) * t / self.decay;
+        }
+        t -= self.decay;
+        if t < self.hold {
+            return self.sustain;
+        }
+        t -= self.hold;
+        if t < self.release {
+            return self.sustain * (1.0 - t / self.release);
+        }
+        0.0
+    }
+}
+
+/// one pole low pass filter
+#[derive(Clone, Copy)]
+pub struct LowPass {
+    alpha: f32,
+    last: f32,
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32) -> Self {
+        Self {
+            alpha: 1.0 - (-TAU * cutoff * DT).exp(),
+            last: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.last += self.alpha * (x - self.last);
+        self.last
+    }
+}
+
+/// a value that can be changed from systems while the sound is playing
+#[derive(Clone, Default)]
+pub struct Param(Arc<AtomicU32>);
+
+impl Param {
+    pub fn new(value: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(value.to_bits())))
+    }
+
+    pub fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Clone)]
+pub enum Patch {
+    /// bell-like note, for kills
+    Chime { frequency: f32 },
+    /// low, harsh and short, for getting hit
+    Buzz,
+    /// plays until stopped, following the parameters
+    Hum { frequency: Param, volume: Param },
+}
+
+/// a sound generated on the fly. play it with `Audio<Synth>`
+#[derive(TypeUuid, Clone)]
+#[uuid = "5d2f8e3a-7c41-4b9e-a6d2-1f0e9c8b7a63"]
+pub struct Synth {
+    pub patch: Patch,
+    /// -1 is fully left, 1 is fully right
+    pub pan: f32,
+    pub gain: f32,
+}
+
+impl Synth {
+    pub fn new(patch: Patch) -> Self {
+        Self {
+            patch,
+            pan: 0.0,
+            gain: 1.0,
+        }
+    }
+
+    /// pans and attenuates the sound depending on where it happens relative to the listener
+    pub fn at(mut self, position: Vec2, listener: Vec2) -> Self {
+        let diff = position - listener;
+        self.pan = (diff.x / (ARENA_WIDTH / 2.0)).clamp(-1.0, 1.0);
+        // a sound at the edge of the arena is at about two thirds of the volume
+        self.gain = 1.0 / (1.0 + diff.length() / ARENA_WIDTH);
+        self
+    }
+}
+
+impl Decodable for Synth {
+    type Decoder = SynthSource;
+    type DecoderItem = f32;
+
+    fn decoder(&self) -> Self::Decoder {
+        let (oscillators, cutoff) = match &self.patch {
+            Patch::Chime { .. } => ([Waveform::Sine, Waveform::Sine], 8000.0),
+            Patch::Buzz => ([Waveform::Saw, Waveform::Square], 900.0),
+            Patch::Hum { .. } => ([Waveform::Triangle, Waveform::Saw], 600.0),
+        };
+        // equal power panning
+        let angle = (self.pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        SynthSource {
+            patch: self.patch.clone(),
+            gains: [angle.cos() * self.gain, angle.sin() * self.gain],
+            right: None,
+            t: 0.0,
+            oscillators: oscillators.map(Oscillator::new),
+            filter: LowPass::new(cutoff),
+            frequency: 0.0,
+            volume: 0.0,
+        }
+    }
+}
+
+pub struct SynthSource {
+    patch: Patch,
+    /// left and right volume
+    gains: [f32; 2],
+    /// right channel of the current frame, samples are interleaved
+    right: Option<f32>,
+    /// seconds since the sound started
+    t: f32,
+    oscillators: [Oscillator; 2],
+    filter: LowPass,
+    /// smoothed values for the hum, so changes don't click
+    frequency: f32,
+    volume: f32,
+}
+
+impl Iterator for SynthSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.right.take() {
+            return Some(right);
+        }
+
+        let sample = self.next_mono()?;
+        self.right = Some(sample * self.gains[1]);
+        Some(sample * self.gains[0])
+    }
+}
+
+impl SynthSource {
+    fn next_mono(&mut self) -> Option<f32> {
+        let t = self.t;
+        self.t += DT;
+        let [a, b] = &mut self.oscillators;
+
+        let sample = match &self.patch {
+            Patch::Chime { frequency } => {
+                let env = Envelope {
+                    attack: 0.005,
+                    decay: 0.8,
+                    sustain: 0.0,
+                    hold: 0.0,
+                    release: 0.0,
+                };
+                if t > env.length() {
+                    return None;
+                }
+                // the overtone dies off faster than the fundamental
+                let s =
+                    a.next(*frequency) * 0.6 + b.next(*frequency * 2.0) * 0.3 * (-t * 8.0).exp();
+                s * env.amplitude(t) * 0.4
+            }
+            Patch::Buzz => {
+                let env = Envelope {
+                    attack: 0.005,
+                    decay: 0.1,
+                    sustain: 0.5,
+                    hold: 0.05,
+                    release: 0.1,
+                };
+                if t > env.length() {
+                    return None;
+                }
+                let s = a.next(70.0) * 0.5 + b.next(73.0) * 0.5;
+                self.filter.process(s) * env.amplitude(t) * 0.5
+            }
+            Patch::Hum { frequency, volume } => {
+                // about 20ms to catch up
+                let k = 1.0 - (-DT / 0.02).exp();
+                self.frequency += (frequency.get() - self.frequency) * k;
+                self.volume += (volume.get() - self.volume) * k;
+
+                let s = a.next(self.frequency) * 0.6 + b.next(self.frequency * 0.5) * 0.4;
+                self.filter.process(s) * self.volume * 0.15
+            }
+        };
+        Some(sample)
+    }
+}
+
+impl rodio::Source for SynthSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// frequency of a degree of the c major scale, starting at c5
+pub fn scale_frequency(degree: usize) -> f32 {
+    const SEMITONES: [usize; 7] = [0, 2, 4, 5, 7, 9, 11];
+    let semitones = SEMITONES[degree % 7] + 12 * (degree / 7);
+    523.25 * 2f32.powf(semitones as f32 / 12.0)
+}
@@ -1,79 +1,399 @@
 use crate::*;
 
-pub fn setup_ui(mut cmd: Commands, a: Res<GameAssets>) {
-    cmd.spawn_bundle(
-        TextBundle::from_section(
-            "health: 30",
-            TextStyle {
-                font: a.font.clone(),
-                font_size: 80.0,
-                color: Color::WHITE,
+pub fn setup_ui(
+    mut cmd: Commands,
+    a: Res<GameAssets>,
+    symbols: Res<EnemySymbols>,
+    palette: Res<Palette>,
+    health: Res<PlayerHealth>,
+) {
+    let style = |size| TextStyle {
+        font: a.font.clone(),
+        font_size: size,
+        color: Color::WHITE,
+    };
+
+    // shield cooldown
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(60.0),
+                right: Val::Px(15.0),
+                ..default()
             },
-        )
-        .with_text_alignment(TextAlignment::TOP_CENTER)
-        .with_style(Style {
-            align_self: AlignSelf::FlexEnd,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(CleanupGame)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section("", style(30.0)).with_style(Style {
+                margin: UiRect {
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }))
+            .insert(Localized::new("hud-shield"));
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(SHIELD_BAR_WIDTH), Val::Px(16.0)),
+                    ..default()
+                },
+                color: Color::rgb(0.2, 0.2, 0.2).into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(SHIELD_BAR_WIDTH), Val::Percent(100.0)),
+                            ..default()
+                        },
+                        color: SHIELD_READY_COLOR.into(),
+                        ..default()
+                    })
+                    .insert(ShieldBar);
+            });
+    });
+
+    // health bar
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
             position_type: PositionType::Absolute,
             position: UiRect {
-                bottom: Val::Px(5.0),
+                bottom: Val::Px(15.0),
                 right: Val::Px(15.0),
                 ..default()
             },
+            align_items: AlignItems::Center,
             ..default()
-        }),
-    )
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
     .insert(CleanupGame)
-    .insert(PlayerHealthText);
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section("", style(40.0)).with_style(Style {
+                margin: UiRect {
+                    right: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }))
+            .insert(Localized::new("hud-health"));
+
+        // the bar stays the same length however much health there is
+        let width = (HEALTH_BAR_WIDTH / health.max.max(1) as f32 - 2.0).max(1.0);
+        for index in 0..health.max {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(width), Val::Px(30.0)),
+                        margin: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    color: HEALTH_SEGMENT_COLOR.into(),
+                    ..default()
+                })
+                .insert(HealthSegment {
+                    index,
+                    width,
+                    lost_at: None,
+                });
+        }
+    });
+
     cmd.spawn_bundle(
-        TextBundle::from_section(
-            "points: 0",
-            TextStyle {
-                font: a.font.clone(),
-                font_size: 80.0,
-                color: Color::WHITE,
-            },
-        )
-        .with_text_alignment(TextAlignment::TOP_CENTER)
-        .with_style(Style {
-            align_self: AlignSelf::FlexEnd,
+        TextBundle::from_section("", style(80.0))
+            .with_text_alignment(TextAlignment::TOP_CENTER)
+            .with_style(Style {
+                align_self: AlignSelf::FlexEnd,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(15.0),
+                    ..default()
+                },
+                ..default()
+            }),
+    )
+    .insert(CleanupGame)
+    .insert(Localized::with_args("hud-points", vec!["0".to_string()]))
+    .insert(PointsText);
+
+    // time and wave
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
             position_type: PositionType::Absolute,
             position: UiRect {
-                bottom: Val::Px(5.0),
+                top: Val::Px(10.0),
                 left: Val::Px(15.0),
                 ..default()
             },
+            flex_direction: FlexDirection::ColumnReverse,
             ..default()
-        }),
-    )
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
     .insert(CleanupGame)
-    .insert(PointsText);
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section("", style(30.0)))
+            .insert(Localized::with_args("hud-time", vec!["0".to_string()]))
+            .insert(TimeText);
+        parent
+            .spawn_bundle(TextBundle::from_section("", style(30.0)))
+            .insert(Localized::with_args("hud-wave", vec!["1".to_string()]))
+            .insert(WaveText);
+    });
+
+    // selected beam
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                left: Val::Percent(45.0),
+                ..default()
+            },
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(CleanupGame)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(TextBundle::from_section("", style(30.0)))
+            .insert(Localized::new("hud-beam"));
+        spawn_colour_chip(parent, Colour::Green, &symbols, &palette).insert(SelectedBeamIndicator);
+    });
+
+    // colour legend
+    cmd.spawn_bundle(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(10.0),
+                right: Val::Px(15.0),
+                ..default()
+            },
+            flex_direction: FlexDirection::ColumnReverse,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    })
+    .insert(CleanupGame)
+    .with_children(|parent| {
+        for colour in [Colour::Yellow, Colour::Magenta, Colour::Cyan, Colour::White] {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_colour_chip(row, colour, &symbols, &palette);
+                    row.spawn_bundle(TextBundle::from_section(" = ", style(20.0)));
+                    for (i, beam) in colour.made_by().into_iter().enumerate() {
+                        if i > 0 {
+                            row.spawn_bundle(TextBundle::from_section(" + ", style(20.0)));
+                        }
+                        spawn_colour_chip(row, beam.colour(), &symbols, &palette);
+                    }
+                });
+        }
+    });
+}
+
+/// a small square of the colour with its symbol on top, like the enemies have
+fn spawn_colour_chip<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    colour: Colour,
+    symbols: &EnemySymbols,
+    palette: &Palette,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut chip = parent.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(24.0), Val::Px(24.0)),
+            ..default()
+        },
+        color: palette.colour(colour).into(),
+        ..default()
+    });
+    chip.with_children(|chip| {
+        chip.spawn_bundle(ImageBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            image: symbols.get(colour).into(),
+            color: Color::BLACK.into(),
+            ..default()
+        });
+    });
+    chip
 }
 
+/// segments plus their margins, in px
+const HEALTH_BAR_WIDTH: f32 = 300.0;
+const HEALTH_SEGMENT_COLOR: Color = Color::rgb(0.9, 0.2, 0.3);
+/// how long a lost segment takes to disappear
+const HEALTH_SEGMENT_FADE: f32 = 0.4;
+
 #[derive(Component)]
-pub struct PlayerHealthText;
+pub struct HealthSegment {
+    index: i8,
+    /// how wide it is while it's not lost
+    width: f32,
+    lost_at: Option<f64>,
+}
+
 pub fn update_player_health_ui(
     health: Res<PlayerHealth>,
-    mut texts: Query<&mut Text, With<PlayerHealthText>>,
+    mut segments: Query<(&mut HealthSegment, &mut Style, &mut UiColor)>,
+    time: Res<Time>,
 ) {
-    if !health.is_changed() {
-        return;
+    let now = time.seconds_since_startup();
+    for (mut segment, mut style, mut color) in &mut segments {
+        if segment.index < health.health {
+            if segment.lost_at.is_some() {
+                segment.lost_at = None;
+                style.size.width = Val::Px(segment.width);
+                *color = HEALTH_SEGMENT_COLOR.into();
+            }
+            continue;
+        }
+
+        let lost_at = *segment.lost_at.get_or_insert(now);
+        let t = ((now - lost_at) as f32 / HEALTH_SEGMENT_FADE).min(1.0);
+
+        // flash white, then shrink away
+        *color = if t < 0.25 {
+            Color::WHITE.into()
+        } else {
+            HEALTH_SEGMENT_COLOR.into()
+        };
+        style.size.width = Val::Px(segment.width * (1.0 - t));
+        style.margin = UiRect::all(Val::Px(1.0 - t));
     }
+}
 
-    for mut text in &mut texts {
-        // Update the color of the first and only section.
-        text.sections[0].value = format!("health: {}", health.health);
+const SHIELD_BAR_WIDTH: f32 = 80.0;
+const SHIELD_READY_COLOR: Color = Color::rgb(0.6, 0.9, 1.0);
+const SHIELD_CHARGING_COLOR: Color = Color::rgb(0.3, 0.4, 0.5);
+
+#[derive(Component)]
+pub struct ShieldBar;
+pub fn update_shield_ui(
+    players: Query<(&Player, &Shield)>,
+    mut bars: Query<(&mut Style, &mut UiColor), With<ShieldBar>>,
+    net: Res<NetSession>,
+) {
+    let local = net.local_player();
+    let charge = match players.iter().find(|(p, _)| p.index == local) {
+        Some((_, shield)) => shield.charge(),
+        None => return,
+    };
+    for (mut style, mut color) in &mut bars {
+        style.size.width = Val::Px(SHIELD_BAR_WIDTH * charge);
+        *color = if charge >= 1.0 {
+            SHIELD_READY_COLOR
+        } else {
+            SHIELD_CHARGING_COLOR
+        }
+        .into();
     }
 }
 
 #[derive(Component)]
 pub struct PointsText;
-pub fn update_points_ui(points: Res<EnemiesKilled>, mut texts: Query<&mut Text, With<PointsText>>) {
-    if !points.is_changed() {
+pub fn update_points_ui(
+    points: Res<EnemiesKilled>,
+    rhythm: Res<RhythmScore>,
+    mode: Res<GameMode>,
+    modifiers: Res<RunModifiers>,
+    mut texts: Query<&mut Localized, With<PointsText>>,
+) {
+    if !points.is_changed() && !rhythm.is_changed() {
         return;
     }
 
     for mut text in &mut texts {
-        // Update the color of the first and only section.
-        text.sections[0].value = format!("points: {}", points.0);
+        // rhythm mode has its own score
+        if *mode == GameMode::Rhythm {
+            text.key = "hud-rhythm-score";
+            text.args = vec![rhythm.score.to_string()];
+        } else {
+            text.key = "hud-points";
+            text.args = vec![modifiers.score(points.0).to_string()];
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TimeText;
+#[derive(Component)]
+pub struct WaveText;
+pub fn update_time_ui(
+    game_start: Res<GameStartTime>,
+    time: Res<Time>,
+    mut times: Query<&mut Localized, (With<TimeText>, Without<WaveText>)>,
+    mut waves: Query<&mut Localized, (With<WaveText>, Without<TimeText>)>,
+) {
+    let elapsed = time.seconds_since_startup() - game_start.0;
+
+    // only touch the text when the shown value changes, so it doesn't get rebuilt every frame
+    let seconds = (elapsed as u64).to_string();
+    for mut text in &mut times {
+        if text.args[0] != seconds {
+            text.args = vec![seconds.clone()];
+        }
+    }
+
+    let wave = current_wave(elapsed).to_string();
+    for mut text in &mut waves {
+        if text.args[0] != wave {
+            text.args = vec![wave.clone()];
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SelectedBeamIndicator;
+pub fn update_selected_beam_ui(
+    players: Query<(&Player, &ClosestBeam), Changed<ClosestBeam>>,
+    palette: Res<Palette>,
+    mut chips: Query<(&Children, &mut UiColor), With<SelectedBeamIndicator>>,
+    mut images: Query<&mut UiImage>,
+    symbols: Res<EnemySymbols>,
+    net: Res<NetSession>,
+) {
+    let local = net.local_player();
+    let colour = match players.iter().find(|(p, _)| p.index == local) {
+        Some((_, beam)) => beam.0.colour(),
+        None => return,
+    };
+    for (children, mut color) in &mut chips {
+        *color = palette.colour(colour).into();
+        for child in children {
+            if let Ok(mut image) = images.get_mut(*child) {
+                *image = symbols.get(colour).into();
+            }
+        }
     }
 }